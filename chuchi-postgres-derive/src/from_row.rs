@@ -7,6 +7,7 @@ use syn::{Fields, FieldsNamed, FieldsUnnamed};
 use syn::{Lifetime, LifetimeParam};
 
 use proc_macro2::{Span, TokenStream};
+use types::ident::Ident;
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -115,7 +116,7 @@ fn parse_named_fields(
 		if !select_columns.is_empty() {
 			select_columns.push_str(", ");
 		}
-		write!(&mut select_columns, "\"{ident_str}\"").unwrap();
+		write!(&mut select_columns, "{}", Ident::new(&ident_str)).unwrap();
 
 		from_stream.extend(quote!(
 			#ident: row.try_get(#ident_str)?,
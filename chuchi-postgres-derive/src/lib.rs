@@ -1,14 +1,17 @@
 mod from_row;
+mod migrations;
 mod row;
 mod table_templ;
 mod to_row;
 
 use from_row::expand_from_row;
+use migrations::expand_embed_migrations;
 use quote::quote;
 
 use row::expand_row;
 use syn::parse_macro_input;
 use syn::DeriveInput;
+use syn::LitStr;
 
 use proc_macro::TokenStream as V1TokenStream;
 
@@ -22,7 +25,7 @@ use crate::row::RowInput;
 
 // inspired from https://github.com/serde-rs/serde/blob/master/serde_derive
 
-#[proc_macro_derive(TableTempl, attributes(len, index, unique))]
+#[proc_macro_derive(TableTempl, attributes(len, index, unique, tsvector))]
 pub fn derive_table_templ(input: V1TokenStream) -> V1TokenStream {
 	let input = parse_macro_input!(input as DeriveInput);
 
@@ -96,6 +99,35 @@ pub fn row(input: V1TokenStream) -> V1TokenStream {
 	expand_row(&input, &name).unwrap_or_else(to_compile_error)
 }
 
+/// Embeds all `{version}_{name}.sql` files of a directory relative to the
+/// crate root as a `MigrationSet`.
+///
+/// A `{version}_{name}.down.sql` file is used as the down script of the
+/// migration with the same version. A migration which starts with the line
+/// `-- no-transaction` runs without a transaction. Files which don't end in
+/// `.sql` are ignored.
+///
+/// ## Note
+/// Cargo does not track new files in the directory, changes to existing
+/// files are tracked.
+#[proc_macro]
+pub fn embed_migrations(input: V1TokenStream) -> V1TokenStream {
+	let input = parse_macro_input!(input as LitStr);
+
+	// crate name
+	let name = crate_name("chuchi-postgres")
+		.expect("chuchi-postgres not in dependencies");
+	let name = match name {
+		FoundCrate::Itself => quote!(crate),
+		FoundCrate::Name(n) => {
+			let ident = Ident::new(&n, Span::call_site());
+			quote!(#ident)
+		}
+	};
+
+	expand_embed_migrations(&input, &name).unwrap_or_else(to_compile_error)
+}
+
 fn to_compile_error(error: syn::Error) -> V1TokenStream {
 	let compile_error = syn::Error::to_compile_error(&error);
 	quote!(#compile_error).into()
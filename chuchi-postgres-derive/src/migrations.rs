@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use syn::{Error, LitStr};
+use types::migrations::MigrationFile;
+
+use ::quote::quote;
+
+/*
+embed_migrations!("migrations")
+
+migrations/0001_init.sql
+migrations/0001_init.down.sql
+migrations/0002_add_users.sql
+
+a migration which starts with the line `-- no-transaction` runs without
+a transaction, files which don't end in .sql are ignored
+*/
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Default)]
+struct MigrationFiles {
+	name: String,
+	up: Option<PathBuf>,
+	down: Option<PathBuf>,
+}
+
+pub fn expand_embed_migrations(
+	input: &LitStr,
+	name: &TokenStream,
+) -> Result<proc_macro::TokenStream> {
+	let manifest_dir = env::var("CARGO_MANIFEST_DIR")
+		.map_err(|_| Error::new(input.span(), "CARGO_MANIFEST_DIR not set"))?;
+	let dir = Path::new(&manifest_dir).join(input.value());
+
+	let entries = fs::read_dir(&dir).map_err(|e| {
+		Error::new(
+			input.span(),
+			format!("could not read {}: {e}", dir.display()),
+		)
+	})?;
+
+	let mut files: BTreeMap<i64, MigrationFiles> = BTreeMap::new();
+
+	for entry in entries {
+		let path = entry
+			.map_err(|e| Error::new(input.span(), e.to_string()))?
+			.path();
+		if !path.is_file() {
+			continue;
+		}
+
+		let file_name = path
+			.file_name()
+			.and_then(|n| n.to_str())
+			.ok_or_else(|| {
+				Error::new(
+					input.span(),
+					format!("invalid file name {}", path.display()),
+				)
+			})?
+			.to_string();
+
+		let Some(file) = MigrationFile::parse(&file_name) else {
+			continue;
+		};
+		let MigrationFile {
+			version,
+			name,
+			is_down,
+		} = file.map_err(|e| Error::new(input.span(), e.to_string()))?;
+
+		let files = files.entry(version).or_default();
+		if !files.name.is_empty() && files.name != name {
+			return Err(Error::new(
+				input.span(),
+				format!(
+					"migration version {version} is used by {} and {name}",
+					files.name
+				),
+			));
+		}
+
+		files.name = name.to_string();
+		let slot = match is_down {
+			true => &mut files.down,
+			false => &mut files.up,
+		};
+		if let Some(prev) = slot {
+			return Err(Error::new(
+				input.span(),
+				format!(
+					"migration version {version} is used by {} and {}",
+					prev.display(),
+					path.display()
+				),
+			));
+		}
+		*slot = Some(path);
+	}
+
+	let migrations = quote!(#name::migrations);
+	let mut adds = quote!();
+
+	for (version, files) in files {
+		let name = files.name;
+		let Some(up) = files.up else {
+			return Err(Error::new(
+				input.span(),
+				format!("migration {name} only has a down script"),
+			));
+		};
+
+		let no_transaction = fs::read_to_string(&up)
+			.map_err(|e| {
+				Error::new(
+					input.span(),
+					format!("could not read {}: {e}", up.display()),
+				)
+			})?
+			.lines()
+			.next()
+			.is_some_and(|l| l.trim() == "-- no-transaction");
+
+		// include_str! makes sure the file get's tracked by cargo
+		let up = path_str(&up, input)?;
+		let mut migration = quote!(
+			#migrations::Migration::new(#version, #name, include_str!(#up))
+		);
+		if let Some(down) = files.down {
+			let down = path_str(&down, input)?;
+			migration.extend(quote!(.with_down(include_str!(#down))));
+		}
+		if no_transaction {
+			migration.extend(quote!(.without_transaction()));
+		}
+
+		adds.extend(quote!(.push(#migration)));
+	}
+
+	Ok(quote!(
+		#migrations::MigrationSet::new()
+			#adds
+	)
+	.into())
+}
+
+fn path_str<'a>(path: &'a Path, input: &LitStr) -> Result<&'a str> {
+	path.to_str().ok_or_else(|| {
+		Error::new(input.span(), format!("invalid path {}", path.display()))
+	})
+}
@@ -8,6 +8,7 @@ use syn::{
 };
 
 use ::quote::quote;
+use types::ident::Ident as SqlIdent;
 
 /*
 row! {
@@ -111,7 +112,7 @@ pub fn expand_row(
 
 	for (i, field) in input.fields.iter().enumerate() {
 		let name = &field.name;
-		let ident = Ident::new(&name, field.name_span);
+		let ident = Ident::new(name, field.name_span);
 		let value = &field.value;
 
 		if !insert_columns.is_empty() {
@@ -120,9 +121,10 @@ pub fn expand_row(
 			update_columns.push_str(", ");
 		}
 		let i = i + 1;
-		write!(&mut insert_columns, "\"{name}\"").unwrap();
+		let quoted = SqlIdent::new(name);
+		write!(&mut insert_columns, "{quoted}").unwrap();
 		write!(&mut insert_values, "${i}").unwrap();
-		write!(&mut update_columns, "\"{name}\" = ${i}").unwrap();
+		write!(&mut update_columns, "{quoted} = ${i}").unwrap();
 
 		struct_fields.extend(quote!(
 			#ident: &'a (dyn #macros::ToSql + std::marker::Sync),
@@ -22,7 +22,14 @@ pub fn expand_table_templ(
 			Fields::Named(fields) => {
 				let ident = &input.ident;
 
-				let info_block = parse_named_fields(fields, name)?;
+				let mut extra_cols = vec![];
+				for attr in &input.attrs {
+					if attr.path().is_ident("tsvector") {
+						extra_cols.push(parse_ts_vector(attr, fields, name)?);
+					}
+				}
+
+				let info_block = parse_named_fields(fields, &extra_cols, name)?;
 
 				let table = quote!(#name::table);
 				let toks = quote!(
@@ -43,9 +50,10 @@ pub fn expand_table_templ(
 
 fn parse_named_fields(
 	fields: &FieldsNamed,
+	extra_cols: &[TokenStream],
 	name: &TokenStream,
 ) -> Result<TokenStream> {
-	let len = fields.named.len();
+	let len = fields.named.len() + extra_cols.len();
 	let mut info_stream = quote!(
 		let mut info = #name::table::Info::with_capacity(#len);
 	);
@@ -55,6 +63,10 @@ fn parse_named_fields(
 		info_stream.extend(quote!(info.push(#col);));
 	}
 
+	for col in extra_cols {
+		info_stream.extend(quote!(info.push(#col);));
+	}
+
 	info_stream.extend(quote!(info));
 
 	Ok(info_stream)
@@ -93,7 +105,7 @@ fn parse_named_field(
 			p if p.is_ident("unique") => {
 				let res: syn::Ident = attr.parse_args()?;
 				let index_str = res.to_string();
-				index = quote!(#index_kind::NamedUnique(#index_str));
+				index = quote!(#index_kind::NamedUnique(#index_str.into()));
 			}
 			_ => {}
 		}
@@ -108,3 +120,84 @@ fn parse_named_field(
 
 	Ok(col)
 }
+
+/// `#[tsvector(name = "search", config = "english", fields(title, body))]`
+fn parse_ts_vector(
+	attr: &syn::Attribute,
+	fields: &FieldsNamed,
+	crate_name: &TokenStream,
+) -> Result<TokenStream> {
+	let mut name: Option<syn::LitStr> = None;
+	let mut config: Option<syn::LitStr> = None;
+	let mut columns = vec![];
+
+	attr.parse_nested_meta(|meta| {
+		if meta.path.is_ident("name") {
+			name = Some(meta.value()?.parse()?);
+		} else if meta.path.is_ident("config") {
+			config = Some(meta.value()?.parse()?);
+		} else if meta.path.is_ident("fields") {
+			meta.parse_nested_meta(|field| {
+				let ident = field.path.require_ident()?;
+				let found = fields
+					.named
+					.iter()
+					.find(|f| f.ident.as_ref() == Some(ident))
+					.ok_or_else(|| err!(ident, "field not found"))?;
+				if !is_text(&found.ty) {
+					return Err(err!(
+						&found.ty,
+						"tsvector fields need to be String, &str, Cow<str> \
+						or an Option of them"
+					));
+				}
+
+				columns.push(ident.to_string());
+				Ok(())
+			})?;
+		} else {
+			return Err(meta.error("expected name, config or fields"));
+		}
+
+		Ok(())
+	})?;
+
+	let name = name.ok_or_else(|| err!(attr, "name is required"))?;
+	if columns.is_empty() {
+		return Err(err!(attr, "fields are required"));
+	}
+	let config = config.map(|c| c.value()).unwrap_or("english".into());
+
+	let table = quote!(#crate_name::table);
+	Ok(quote!(
+		#table::column::Column::ts_vector(#name, #config, [#(#columns),*])
+	))
+}
+
+/// Checks the type syntactically since the derive can't resolve aliases
+fn is_text(ty: &Type) -> bool {
+	match ty {
+		Type::Reference(r) => {
+			matches!(&*r.elem, Type::Path(p) if p.path.is_ident("str"))
+		}
+		Type::Path(p) => {
+			let Some(last) = p.path.segments.last() else {
+				return false;
+			};
+
+			match last.ident.to_string().as_str() {
+				"String" | "Cow" => true,
+				"Option" => match &last.arguments {
+					syn::PathArguments::AngleBracketed(args) => {
+						args.args.iter().any(
+							|arg| matches!(arg, syn::GenericArgument::Type(t) if is_text(t)),
+						)
+					}
+					_ => false,
+				},
+				_ => false,
+			}
+		}
+		_ => false,
+	}
+}
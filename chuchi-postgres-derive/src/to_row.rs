@@ -6,6 +6,7 @@ use syn::Fields;
 use syn::{DeriveInput, Error};
 
 use proc_macro2::TokenStream;
+use types::ident::Ident;
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -44,10 +45,10 @@ pub fn expand_to_row(
 						update_columns.push_str(", ");
 					}
 					let i = i + 1;
-					write!(&mut insert_columns, "\"{ident_str}\"").unwrap();
+					let quoted = Ident::new(&ident_str);
+					write!(&mut insert_columns, "{quoted}").unwrap();
 					write!(&mut insert_values, "${i}").unwrap();
-					write!(&mut update_columns, "\"{ident_str}\" = ${i}")
-						.unwrap();
+					write!(&mut update_columns, "{quoted} = ${i}").unwrap();
 
 					params.extend(quote!(
 						&self.#ident as &(dyn #macros::ToSql + std::marker::Sync),
@@ -0,0 +1,113 @@
+//! Quoting of sql identifiers
+//!
+//! Every table, column or alias name which get's written into sql should go
+//! through [`Ident`].
+
+use std::fmt;
+
+/// A quoted sql identifier like a table or column name
+///
+/// Double quotes inside the name get escaped so any name is safe to use.
+///
+/// ## Example
+/// ```
+/// use chuchi_postgres_types::ident::Ident;
+///
+/// assert_eq!(Ident::new("user").to_string(), r#""user""#);
+/// assert_eq!(Ident::new("a\"b").to_string(), r#""a""b""#);
+/// assert_eq!(Ident::table("auth.users").to_string(), r#""auth"."users""#);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ident<'a> {
+	schema: Option<&'a str>,
+	name: &'a str,
+}
+
+impl<'a> Ident<'a> {
+	/// A single identifier, a `.` is part of the name
+	pub fn new(name: &'a str) -> Self {
+		Self { schema: None, name }
+	}
+
+	/// A table name which can be qualified with a schema `schema.table`
+	pub fn table(name: &'a str) -> Self {
+		match name.split_once('.') {
+			Some((schema, name)) => Self::with_schema(schema, name),
+			None => Self::new(name),
+		}
+	}
+
+	/// A name qualified with a schema, a `.` is part of the schema or name
+	pub fn with_schema(schema: &'a str, name: &'a str) -> Self {
+		Self {
+			schema: Some(schema),
+			name,
+		}
+	}
+
+	/// Returns the name without the schema
+	pub fn name(&self) -> &'a str {
+		self.name
+	}
+
+	pub fn schema(&self) -> Option<&'a str> {
+		self.schema
+	}
+}
+
+impl<'a> From<&'a str> for Ident<'a> {
+	/// See [`Ident::table`]
+	fn from(name: &'a str) -> Self {
+		Self::table(name)
+	}
+}
+
+impl<'a> From<&'a String> for Ident<'a> {
+	/// See [`Ident::table`]
+	fn from(name: &'a String) -> Self {
+		Self::table(name)
+	}
+}
+
+fn fmt_part(part: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+	f.write_str("\"")?;
+
+	for (i, s) in part.split('"').enumerate() {
+		if i != 0 {
+			f.write_str("\"\"")?;
+		}
+
+		f.write_str(s)?;
+	}
+
+	f.write_str("\"")
+}
+
+impl fmt::Display for Ident<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(schema) = self.schema {
+			fmt_part(schema, f)?;
+			f.write_str(".")?;
+		}
+
+		fmt_part(self.name, f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escape() {
+		assert_eq!(Ident::new("a.b").to_string(), r#""a.b""#);
+		assert_eq!(Ident::new(r#"x"; DROP"#).to_string(), r#""x""; DROP""#);
+		assert_eq!(Ident::table("s.t").to_string(), r#""s"."t""#);
+		assert_eq!(Ident::table("s.t").name(), "t");
+		assert_eq!(Ident::table("t").to_string(), r#""t""#);
+		assert_eq!(
+			Ident::with_schema("s.x", "t.y").to_string(),
+			r#""s.x"."t.y""#
+		);
+	}
+}
@@ -1,4 +1,6 @@
+pub mod ident;
 #[cfg(feature = "json")]
 pub mod json;
+pub mod migrations;
 pub mod time;
 pub mod uid;
@@ -0,0 +1,96 @@
+//! The file names of a migrations directory
+//!
+//! Shared by `embed_migrations!` and `MigrationSet::from_dir` so both read a
+//! directory the same way.
+
+use std::fmt;
+
+/// A file `{version}_{name}.sql` or `{version}_{name}.down.sql`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationFile<'a> {
+	pub version: i64,
+	/// the name without the version and `.sql` or `.down.sql`
+	pub name: &'a str,
+	pub is_down: bool,
+}
+
+impl<'a> MigrationFile<'a> {
+	/// Returns None if the file is not an `.sql` file and should be ignored,
+	/// like a `.gitkeep` or `README.md`
+	pub fn parse(
+		file_name: &'a str,
+	) -> Option<Result<Self, InvalidMigrationFile>> {
+		let stem = file_name.strip_suffix(".sql")?;
+		let (stem, is_down) = match stem.strip_suffix(".down") {
+			Some(stem) => (stem, true),
+			None => (stem, false),
+		};
+
+		let parsed = stem.split_once('_').and_then(|(version, name)| {
+			if version.is_empty()
+				|| !version.bytes().all(|b| b.is_ascii_digit())
+				|| name.is_empty()
+			{
+				return None;
+			}
+
+			Some(Self {
+				version: version.parse().ok()?,
+				name,
+				is_down,
+			})
+		});
+
+		Some(parsed.ok_or_else(|| InvalidMigrationFile(file_name.to_string())))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidMigrationFile(pub String);
+
+impl fmt::Display for InvalidMigrationFile {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"invalid migration {}, expected {{version}}_{{name}}.sql or \
+			{{version}}_{{name}}.down.sql for example 0001_init.sql",
+			self.0
+		)
+	}
+}
+
+impl std::error::Error for InvalidMigrationFile {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(file_name: &str) -> Option<Result<MigrationFile<'_>, ()>> {
+		MigrationFile::parse(file_name).map(|r| r.map_err(|_| ()))
+	}
+
+	#[test]
+	fn file_names() {
+		assert_eq!(
+			parse("0001_init.sql"),
+			Some(Ok(MigrationFile {
+				version: 1,
+				name: "init",
+				is_down: false
+			}))
+		);
+		assert_eq!(
+			parse("0012_add_users.down.sql"),
+			Some(Ok(MigrationFile {
+				version: 12,
+				name: "add_users",
+				is_down: true
+			}))
+		);
+		assert_eq!(parse("init.sql"), Some(Err(())));
+		assert_eq!(parse("0001_.sql"), Some(Err(())));
+		assert_eq!(parse("v1_init.sql"), Some(Err(())));
+		assert_eq!(parse(".gitkeep"), None);
+		assert_eq!(parse("README.md"), None);
+	}
+}
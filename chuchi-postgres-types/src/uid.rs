@@ -19,6 +19,8 @@ use rand::{rngs::OsRng, RngCore};
 pub struct UniqueId([u8; 10]);
 
 impl UniqueId {
+	// a random default would be surprising
+	#[allow(clippy::new_without_default)]
 	pub fn new() -> Self {
 		let secs_bytes = SystemTime::now()
 			.duration_since(UNIX_EPOCH)
@@ -2,6 +2,7 @@
 
 use std::borrow::Borrow;
 use std::fmt::Write;
+use std::ops::{Deref, DerefMut};
 
 use deadpool_postgres::Metrics;
 use deadpool_postgres::{ClientWrapper, Object};
@@ -15,18 +16,24 @@ use tokio_postgres::Error as PgError;
 
 pub use deadpool::managed::TimeoutType;
 pub use deadpool_postgres::{Config, ConfigError};
+use tokio::runtime::Handle;
 use tokio_postgres::Statement;
 use tokio_postgres::ToStatement;
 use tracing::error;
 
 use crate::filter::Filter;
 use crate::filter::Limit;
+use crate::filter::Offset;
 use crate::filter::WhereFilter;
+use crate::keyset::CursorError;
+use crate::paginate::Page;
 use crate::row::NamedColumns;
 use crate::row::RowStream;
 use crate::row::ToRowStatic;
 use crate::row::{FromRowOwned, ToRow};
+use crate::table::{self, Info, TableInfoError};
 use crate::try2;
+use crate::Ident;
 use crate::Row;
 
 #[derive(Debug, thiserror::Error)]
@@ -38,6 +45,15 @@ pub enum Error {
 	#[error("Expected one row")]
 	ExpectedOneRow,
 
+	#[error("Invalid cursor {0}")]
+	Cursor(#[from] CursorError),
+
+	#[error("Page {page} with {per_page} rows per page is out of range")]
+	PageOutOfRange { page: u64, per_page: u64 },
+
+	#[error("Expected {tables} tables but the query only has {aliases}")]
+	JoinTables { tables: usize, aliases: usize },
+
 	#[error("Other Postgres error {0}")]
 	Other(PgError),
 
@@ -68,7 +84,7 @@ impl From<PgError> for Error {
 pub struct ConnectionOwned(pub(crate) Object);
 
 impl ConnectionOwned {
-	pub fn connection(&self) -> Connection {
+	pub fn connection(&self) -> Connection<'_> {
 		Connection {
 			inner: ConnectionInner::Client(&self.0),
 		}
@@ -87,6 +103,60 @@ impl ConnectionOwned {
 	}
 }
 
+/// A connection which uses a schema as `search_path`
+///
+/// Returned by [`Database::get_with_schema`](crate::Database::get_with_schema).
+/// When dropped the `search_path` get's reset before the connection is
+/// returned to the pool, if that fails the connection is closed.
+#[derive(Debug)]
+pub struct SchemaConnection {
+	inner: Option<ConnectionOwned>,
+}
+
+impl SchemaConnection {
+	pub(crate) fn new(conn: ConnectionOwned) -> Self {
+		Self { inner: Some(conn) }
+	}
+}
+
+impl Deref for SchemaConnection {
+	type Target = ConnectionOwned;
+
+	fn deref(&self) -> &ConnectionOwned {
+		self.inner.as_ref().unwrap()
+	}
+}
+
+impl DerefMut for SchemaConnection {
+	fn deref_mut(&mut self) -> &mut ConnectionOwned {
+		self.inner.as_mut().unwrap()
+	}
+}
+
+impl Drop for SchemaConnection {
+	fn drop(&mut self) {
+		let Some(conn) = self.inner.take() else {
+			return;
+		};
+
+		// without a runtime the search_path can't be reset, so the
+		// connection should not be reused
+		let Ok(handle) = Handle::try_current() else {
+			let _ = Object::take(conn.0);
+			return;
+		};
+
+		handle.spawn(async move {
+			let res =
+				conn.connection().batch_execute("RESET search_path").await;
+			if let Err(e) = res {
+				error!("could not reset search_path {e}");
+				let _ = Object::take(conn.0);
+			}
+		});
+	}
+}
+
 #[cfg(feature = "chuchi")]
 mod impl_chuchi {
 	use chuchi::{
@@ -162,14 +232,15 @@ impl Connection<'_> {
 	// or select("table", &["column1", "column2"], filter)
 	pub async fn select<R>(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		filter: impl Borrow<Filter<'_>>,
 	) -> Result<Vec<R>, Error>
 	where
 		R: FromRowOwned + NamedColumns,
 	{
+		let table: Ident = table.into();
 		let sql = format!(
-			"SELECT {} FROM \"{}\"{}",
+			"SELECT {} FROM {}{}",
 			R::select_columns(),
 			table,
 			filter.borrow()
@@ -190,12 +261,13 @@ impl Connection<'_> {
 	// select_one
 	pub async fn select_one<R>(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		filter: impl Borrow<Filter<'_>>,
 	) -> Result<R, Error>
 	where
 		R: FromRowOwned + NamedColumns,
 	{
+		let table: Ident = table.into();
 		let mut formatter = filter.borrow().to_formatter();
 
 		if matches!(formatter.limit, Limit::All) {
@@ -203,7 +275,7 @@ impl Connection<'_> {
 		}
 
 		let sql = format!(
-			"SELECT {} FROM \"{}\"{}",
+			"SELECT {} FROM {}{}",
 			R::select_columns(),
 			table,
 			formatter
@@ -221,12 +293,13 @@ impl Connection<'_> {
 	// select_opt
 	pub async fn select_opt<R>(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		filter: impl Borrow<Filter<'_>>,
 	) -> Result<Option<R>, Error>
 	where
 		R: FromRowOwned + NamedColumns,
 	{
+		let table: Ident = table.into();
 		let mut formatter = filter.borrow().to_formatter();
 
 		if matches!(formatter.limit, Limit::All) {
@@ -234,7 +307,7 @@ impl Connection<'_> {
 		}
 
 		let sql = format!(
-			"SELECT {} FROM \"{}\"{}",
+			"SELECT {} FROM {}{}",
 			R::select_columns(),
 			table,
 			formatter
@@ -251,12 +324,15 @@ impl Connection<'_> {
 	/// indexes on it, this makes the call a lot cheaper
 	pub async fn count(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		column: &str,
 		filter: impl Borrow<Filter<'_>>,
 	) -> Result<u64, Error> {
+		let table: Ident = table.into();
 		let sql = format!(
-			"SELECT COUNT(\"{column}\") FROM \"{table}\"{}",
+			"SELECT COUNT({}) FROM {}{}",
+			Ident::new(column),
+			table,
 			filter.borrow()
 		);
 		let stmt = self.prepare_cached(&sql).await?;
@@ -269,12 +345,113 @@ impl Connection<'_> {
 		Ok(row.get::<_, i64>(0) as u64)
 	}
 
+	/// Selects one page and the total count of matching rows
+	///
+	/// The total is selected with `COUNT(*) OVER()` in the same query, only
+	/// if the page is empty a separate count query is needed. The limit and
+	/// offset of the filter get replaced.
+	pub async fn paginate<R>(
+		&self,
+		table: impl Into<Ident<'_>>,
+		filter: impl Borrow<Filter<'_>>,
+		page: u64,
+		per_page: u64,
+	) -> Result<Page<R>, Error>
+	where
+		R: FromRowOwned + NamedColumns,
+	{
+		let table: Ident = table.into();
+		let filter = filter.borrow();
+		let out_of_range = || Error::PageOutOfRange { page, per_page };
+		let limit = Limit::Fixed(
+			usize::try_from(per_page).map_err(|_| out_of_range())?,
+		);
+		let offset = page
+			.checked_mul(per_page)
+			.and_then(|offset| usize::try_from(offset).ok())
+			.ok_or_else(out_of_range)?;
+		let offset = Offset::Fixed(offset);
+
+		let mut formatter = filter.to_formatter();
+		formatter.limit = &limit;
+		formatter.offset = &offset;
+		// the params of the replaced limit and offset are not used
+		let params_len = formatter.params_len();
+
+		let sql = format!(
+			"SELECT {}, COUNT(*) OVER() AS \"__total\" FROM {}{}",
+			R::select_columns(),
+			table,
+			formatter
+		);
+		let stmt = self.prepare_cached(&sql).await?;
+
+		let rows: Vec<Row> = self
+			.query_raw(
+				&stmt,
+				filter
+					.params
+					.iter_to_sql()
+					.take(params_len)
+					.collect::<Vec<_>>(),
+			)
+			.await?
+			.try_collect()
+			.await?;
+
+		let total = match rows.first() {
+			Some(row) => row.try_get::<_, i64>("__total")? as u64,
+			None if page == 0 => 0,
+			None => {
+				formatter.limit = &Limit::All;
+				formatter.offset = &Offset::Zero;
+
+				let sql = format!(
+					"SELECT COUNT(*) FROM (SELECT 1 FROM {}{}) AS \"__rows\"",
+					table, formatter
+				);
+				let stmt = self.prepare_cached(&sql).await?;
+
+				let row: Row = self
+					.query_raw_opt(
+						&stmt,
+						filter
+							.params
+							.iter_to_sql()
+							.take(params_len)
+							.collect::<Vec<_>>(),
+					)
+					.await
+					.and_then(|opt| opt.ok_or(Error::ExpectedOneRow))?;
+
+				row.get::<_, i64>(0) as u64
+			}
+		};
+
+		let items = rows
+			.into_iter()
+			.map(|row| R::from_row_owned(row).map_err(Error::Deserialize))
+			.collect::<Result<_, _>>()?;
+
+		Ok(Page {
+			items,
+			total,
+			page,
+			per_page,
+		})
+	}
+
 	// insert one
-	pub async fn insert<U>(&self, table: &str, item: &U) -> Result<(), Error>
+	pub async fn insert<U>(
+		&self,
+		table: impl Into<Ident<'_>>,
+		item: &U,
+	) -> Result<(), Error>
 	where
 		U: ToRow,
 	{
-		let mut sql = format!("INSERT INTO \"{table}\" (");
+		let table: Ident = table.into();
+		let mut sql = format!("INSERT INTO {} (", table);
 		item.insert_columns(&mut sql);
 		sql.push_str(") VALUES (");
 		item.insert_values(&mut sql);
@@ -288,7 +465,7 @@ impl Connection<'_> {
 	// insert_many
 	pub async fn insert_many<U, I>(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		items: I,
 	) -> Result<(), Error>
 	where
@@ -296,8 +473,9 @@ impl Connection<'_> {
 		I: IntoIterator,
 		I::Item: Borrow<U>,
 	{
+		let table: Ident = table.into();
 		let sql = format!(
-			"INSERT INTO \"{}\" ({}) VALUES ({})",
+			"INSERT INTO {} ({}) VALUES ({})",
 			table,
 			U::insert_columns(),
 			U::insert_values()
@@ -314,18 +492,19 @@ impl Connection<'_> {
 	// update
 	pub async fn update<U>(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		item: &U,
 		filter: impl Borrow<WhereFilter<'_>>,
 	) -> Result<(), Error>
 	where
 		U: ToRow,
 	{
+		let table: Ident = table.into();
 		let filter = filter.borrow();
 		let mut formatter = filter.whr.to_formatter();
 		formatter.param_start = item.params_len();
 
-		let mut sql = format!("UPDATE \"{table}\" SET ");
+		let mut sql = format!("UPDATE {} SET ", table);
 		item.update_columns(&mut sql);
 		write!(&mut sql, "{}", formatter).unwrap();
 
@@ -344,10 +523,11 @@ impl Connection<'_> {
 	// delete
 	pub async fn delete(
 		&self,
-		table: &str,
+		table: impl Into<Ident<'_>>,
 		filter: impl Borrow<WhereFilter<'_>>,
 	) -> Result<(), Error> {
-		let sql = format!("DELETE FROM \"{}\"{}", table, filter.borrow());
+		let table: Ident = table.into();
+		let sql = format!("DELETE FROM {}{}", table, filter.borrow());
 		let stmt = self.prepare_cached(&sql).await?;
 
 		self.execute_raw(&stmt, filter.borrow().params.iter_to_sql())
@@ -420,6 +600,30 @@ impl Connection<'_> {
 		}
 	}
 
+	/// Reads the columns, indexes and check constraints of a table from the
+	/// database
+	///
+	/// This allows to compare what [`TableTemplate::table_info`] expects with
+	/// what is actually deployed.
+	///
+	/// Unique indexes created by a [`TableTemplate`] are returned as
+	/// [`IndexKind::NamedUnique`] with the name given in the template. Other
+	/// unique indexes over multiple columns keep the name of the index, like
+	/// `t_a_b_key`, and won't compare equal. Columns with a type which has no
+	/// exact [`ColumnKind`] return an error.
+	///
+	/// [`TableTemplate::table_info`]: crate::table::TableTemplate::table_info
+	/// [`TableTemplate`]: crate::table::TableTemplate
+	/// [`IndexKind::NamedUnique`]: crate::table::column::IndexKind::NamedUnique
+	/// [`ColumnKind`]: crate::table::column::ColumnKind
+	pub async fn table_info(
+		&self,
+		table: impl Into<Ident<'_>>,
+	) -> Result<Info, TableInfoError> {
+		let table: Ident = table.into();
+		table::introspect::table_info(self, table).await
+	}
+
 	/// See [`tokio_postgres::Client::query()`]
 	pub async fn query<R, T>(
 		&self,
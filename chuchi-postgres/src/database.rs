@@ -1,15 +1,18 @@
 use deadpool_postgres::{CreatePoolError, Pool, PoolError, Runtime};
 
+use std::time::Duration;
+
 use tokio_postgres::Error as PgError;
 use tokio_postgres::NoTls;
 
 pub use deadpool::managed::TimeoutType;
 pub use deadpool_postgres::{Config as PgConfig, ConfigError};
 
-use crate::connection::ConnectionOwned;
-use crate::migrations::Migrations;
+use crate::connection::{ConnectionOwned, SchemaConnection};
+use crate::migrations::{MigrationError, MigrationLock, Migrations};
 use crate::table::TableOwned;
 use crate::table::TableTemplate;
+use crate::Ident;
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -23,6 +26,9 @@ pub enum DatabaseError {
 	#[error("Connection error {0}")]
 	Connection(#[from] crate::Error),
 
+	#[error("Migration error {0}")]
+	Migration(#[from] MigrationError),
+
 	#[error("Postgres error {0}")]
 	Other(#[from] PgError),
 }
@@ -54,6 +60,7 @@ mod impl_chuchi {
 pub struct Config {
 	pg_config: PgConfig,
 	migration_table: Option<String>,
+	migration_lock: MigrationLock,
 }
 
 impl Config {
@@ -95,6 +102,22 @@ impl Config {
 		self
 	}
 
+	/// Set's what happens if another process is running migrations, by
+	/// default it waits until the migrations are done.
+	pub fn migration_lock(mut self, lock: MigrationLock) -> Self {
+		self.migration_lock = lock;
+		self
+	}
+
+	/// Set's how long to wait for another process to finish its migrations
+	/// before returning an error.
+	pub fn migration_lock_timeout(mut self, timeout: Duration) -> Self {
+		self.migration_lock = MigrationLock::Wait {
+			timeout: Some(timeout),
+		};
+		self
+	}
+
 	/// Get's a reference to the `PgConfig`.
 	pub fn pg_config(&self) -> &PgConfig {
 		&self.pg_config
@@ -144,6 +167,12 @@ impl Database {
 
 	/// Create a new database with a custom configuration.
 	pub async fn with_cfg(cfg: Config) -> Result<Self, DatabaseError> {
+		// the migrations lock uses it's own connection
+		let pg_config = cfg
+			.pg_config
+			.get_pg_config()
+			.map_err(DatabaseError::Config)?;
+
 		let pool = cfg
 			.pg_config
 			.create_pool(Some(Runtime::Tokio1), NoTls)
@@ -156,7 +185,11 @@ impl Database {
 
 		let this = Self {
 			pool,
-			migrations: Migrations::new(cfg.migration_table),
+			migrations: Migrations::new(
+				cfg.migration_table,
+				cfg.migration_lock,
+				pg_config,
+			),
 		};
 
 		// just make sure the connection worked
@@ -184,6 +217,28 @@ impl Database {
 			.map(ConnectionOwned)
 	}
 
+	/// Get a connection which uses the given schema as `search_path`.
+	///
+	/// Unqualified table names will resolve to tables in this schema, which
+	/// allows to use a schema per tenant.
+	///
+	/// The `search_path` get's reset when the connection is dropped, before
+	/// it is returned to the pool.
+	pub async fn get_with_schema(
+		&self,
+		schema: &str,
+	) -> Result<SchemaConnection, DatabaseError> {
+		let conn = self.get().await?;
+		conn.connection()
+			.batch_execute(&format!(
+				"SET search_path TO {}",
+				Ident::new(schema)
+			))
+			.await?;
+
+		Ok(SchemaConnection::new(conn))
+	}
+
 	/// Get the migrations.
 	pub fn migrations(&self) -> Migrations {
 		self.migrations.clone()
@@ -197,6 +252,18 @@ impl Database {
 	where
 		T: TableTemplate,
 	{
-		TableOwned::new(self.clone(), name)
+		TableOwned::new(self.clone(), None, name)
+	}
+
+	/// Get a table which lives in the given schema
+	pub fn table_owned_with_schema<T>(
+		&self,
+		schema: &'static str,
+		name: &'static str,
+	) -> TableOwned<T>
+	where
+		T: TableTemplate,
+	{
+		TableOwned::new(self.clone(), Some(schema), name)
 	}
 }
@@ -42,7 +42,7 @@ pub struct ConnOwned {
 
 impl ConnOwned {
 	// connection
-	pub fn conn(&self) -> Conn {
+	pub fn conn(&self) -> Conn<'_> {
 		Conn {
 			pg: self.pg.as_ref().map(|pg| pg.connection()),
 		}
@@ -50,7 +50,7 @@ impl ConnOwned {
 
 	// or transaction
 	#[allow(dead_code)]
-	pub async fn trans(&mut self) -> Result<Trans, Error> {
+	pub async fn trans(&mut self) -> Result<Trans<'_>, Error> {
 		match &mut self.pg {
 			Some(pg) => Ok(Trans {
 				pg: Some(pg.transaction().await?),
@@ -115,7 +115,7 @@ pub struct Trans<'a> {
 
 impl<'a> Trans<'a> {
 	/// Get the connection of the transaction.
-	pub fn conn(&self) -> Conn {
+	pub fn conn(&self) -> Conn<'_> {
 		Conn {
 			pg: self.pg.as_ref().map(|pg| pg.connection()),
 		}
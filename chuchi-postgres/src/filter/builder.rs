@@ -0,0 +1,323 @@
+use tokio_postgres::types::ToSql;
+
+use super::{
+	param_name, ColumnRef, Filter, Limit, Offset, Operator, Param, ParamData,
+	TsQuery, Where, WhereOperation, WherePart,
+};
+
+/// Builds a [`Filter`] at runtime
+///
+/// Conditions are combined with `AND` unless [`or`](Self::or) is called
+/// before them, this makes it easy to add optional conditions.
+///
+/// ## Example
+/// ```
+/// use chuchi_postgres::filter::Filter;
+///
+/// let name = "Alice".to_string();
+/// let min_age: Option<i32> = Some(18);
+///
+/// let mut builder = Filter::build().eq("name", &name);
+/// if let Some(age) = min_age {
+/// 	builder = builder.gt("age", age);
+/// }
+/// let filter = builder.order_desc("created").limit(20).finish();
+///
+/// assert_eq!(
+/// 	filter.to_string(),
+/// 	r#" WHERE "name" = $1 AND "age" > $2 ORDER BY "created" DESC LIMIT 20"#
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct FilterBuilder<'a> {
+	filter: Filter<'a>,
+	/// the operator before the next condition
+	next: Option<WherePart>,
+}
+
+impl<'a> FilterBuilder<'a> {
+	pub fn new() -> Self {
+		Self {
+			filter: Filter::new(),
+			next: None,
+		}
+	}
+
+	/// Combines the next condition with `AND`, which is the default
+	pub fn and(mut self) -> Self {
+		self.next = Some(WherePart::And);
+		self
+	}
+
+	/// Combines the next condition with `OR`
+	pub fn or(mut self) -> Self {
+		self.next = Some(WherePart::Or);
+		self
+	}
+
+	fn push(&mut self, part: WherePart) {
+		let next = self.next.take().unwrap_or(WherePart::And);
+		if !self.filter.whr.is_empty() {
+			self.filter.whr.push(next);
+		}
+
+		self.filter.whr.push(part);
+	}
+
+	fn op<T>(
+		mut self,
+		column: impl Into<ColumnRef>,
+		kind: Operator,
+		value: T,
+	) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		let column = column.into();
+		let param = Param::new_owned(param_name(&column.name), value);
+
+		// the same as filter!
+		let kind = match kind {
+			Operator::Eq if param.is_null() => Operator::IsNull,
+			Operator::Ne if param.is_null() => Operator::IsNotNull,
+			kind => {
+				self.filter.params.push(param);
+				kind
+			}
+		};
+
+		self.push(WhereOperation { kind, column }.into());
+		self
+	}
+
+	/// `"column" = $1` or `"column" IS NULL` if the value is null
+	pub fn eq<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Eq, value)
+	}
+
+	/// `"column" != $1` or `"column" IS NOT NULL` if the value is null
+	pub fn ne<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Ne, value)
+	}
+
+	pub fn lt<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Lt, value)
+	}
+
+	pub fn lte<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Lte, value)
+	}
+
+	pub fn gt<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Gt, value)
+	}
+
+	pub fn gte<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Gte, value)
+	}
+
+	pub fn like<T>(self, column: impl Into<ColumnRef>, value: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Like, value)
+	}
+
+	/// `"column" = ANY($1)` binds the list as one array
+	pub fn any<T>(self, column: impl Into<ColumnRef>, values: T) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::InArray, values)
+	}
+
+	/// `"column" IN ($1, $2)` or `1=0` if there are no values
+	pub fn is_in<I, T>(
+		mut self,
+		column: impl Into<ColumnRef>,
+		values: I,
+	) -> Self
+	where
+		I: IntoIterator<Item = T>,
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		let column = column.into();
+		let name = param_name(&column.name);
+
+		let mut length = 0;
+		for value in values {
+			self.filter.params.push(Param::new_owned(name, value));
+			length += 1;
+		}
+
+		self.push(
+			WhereOperation {
+				kind: Operator::In { length },
+				column,
+			}
+			.into(),
+		);
+		self
+	}
+
+	/// `"column" @@ to_tsquery($1)` with the function of `query`
+	pub fn search<T>(
+		self,
+		column: impl Into<ColumnRef>,
+		query: TsQuery,
+		value: T,
+	) -> Self
+	where
+		T: ParamData + ToSql + Send + Sync + 'a,
+	{
+		self.op(column, Operator::Search(query), value)
+	}
+
+	pub fn is_null(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.push(
+			WhereOperation {
+				kind: Operator::IsNull,
+				column: column.into(),
+			}
+			.into(),
+		);
+		self
+	}
+
+	pub fn is_not_null(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.push(
+			WhereOperation {
+				kind: Operator::IsNotNull,
+				column: column.into(),
+			}
+			.into(),
+		);
+		self
+	}
+
+	fn group<F>(mut self, not: bool, f: F) -> Self
+	where
+		F: FnOnce(Self) -> Self,
+	{
+		let inner = f(Self::new()).filter;
+		self.filter.params.append(inner.params);
+
+		if !inner.whr.is_empty() {
+			self.push(match not {
+				true => WherePart::Not(inner.whr),
+				false => WherePart::Nested(inner.whr),
+			});
+		}
+
+		self
+	}
+
+	/// `(...)` only the conditions of the inner builder are used
+	pub fn nested<F>(self, f: F) -> Self
+	where
+		F: FnOnce(Self) -> Self,
+	{
+		self.group(false, f)
+	}
+
+	/// `NOT (...)` only the conditions of the inner builder are used
+	pub fn not<F>(self, f: F) -> Self
+	where
+		F: FnOnce(Self) -> Self,
+	{
+		self.group(true, f)
+	}
+
+	pub fn order_asc(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.filter.order_by.push_asc(column);
+		self
+	}
+
+	pub fn order_desc(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.filter.order_by.push_desc(column);
+		self
+	}
+
+	pub fn limit(mut self, limit: usize) -> Self {
+		self.filter.limit = Limit::Fixed(limit);
+		self
+	}
+
+	pub fn offset(mut self, offset: usize) -> Self {
+		self.filter.offset = Offset::Fixed(offset);
+		self
+	}
+
+	/// Returns the conditions which where added until now
+	pub fn whr(&self) -> &Where {
+		&self.filter.whr
+	}
+
+	pub fn finish(self) -> Filter<'a> {
+		self.filter
+	}
+}
+
+impl Default for FilterBuilder<'_> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<'a> From<FilterBuilder<'a>> for Filter<'a> {
+	fn from(builder: FilterBuilder<'a>) -> Self {
+		builder.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::filter;
+
+	#[test]
+	fn same_as_macro() {
+		let name = "Alice";
+		let age = 18;
+		let none: Option<i32> = None;
+		let ids = [1, 2];
+
+		let built = Filter::build()
+			.eq("name", name)
+			.or()
+			.nested(|b| b.gt("age", age).is_in("id", ids).eq("c", none))
+			.not(|b| b.ne("name", "Bob"))
+			.order_desc("created")
+			.limit(20)
+			.finish();
+		let macro_filter = filter!(
+			"name" = &name
+			OR ("age" > &age AND "id" IN &ids AND "c" = &none)
+			AND NOT ("name" != &"Bob")
+			ORDER "created" DESC
+			LIMIT 20
+		);
+
+		assert_eq!(built.to_string(), macro_filter.to_string());
+		assert_eq!(built.params.len(), macro_filter.params.len());
+	}
+}
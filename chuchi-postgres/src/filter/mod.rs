@@ -1,15 +1,24 @@
 use std::borrow::Cow;
 use std::fmt;
+use std::fmt::Write;
+use std::sync::Arc;
 
 use tokio_postgres::types::ToSql;
 use types::time::{Date, DateTime, Timeout};
 use types::uid::UniqueId;
 
+use crate::Ident;
+
+mod builder;
+mod query_string;
 mod whr;
 
+pub use builder::FilterBuilder;
+pub use query_string::{parse_query_string, QueryStringError};
+
 pub type SqlStr = Cow<'static, str>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct Filter<'a> {
 	pub whr: Where,
@@ -30,17 +39,111 @@ impl<'a> Filter<'a> {
 		}
 	}
 
+	/// Returns a builder to create a filter at runtime
+	pub fn build() -> FilterBuilder<'a> {
+		FilterBuilder::new()
+	}
+
+	/// Combines both filters with `AND`
+	///
+	/// The order of `other` get's appended to the order of `self`. The limit
+	/// and offset of `self` are kept if they are set, else the ones from
+	/// `other` are used. An empty where matches every row, so it is ignored.
+	pub fn and(self, other: Filter<'a>) -> Self {
+		self.combine(WherePart::And, other)
+	}
+
+	/// Combines both filters with `OR`
+	///
+	/// An empty where matches every row, so if one of the wheres is empty the
+	/// result has no where. See [`Filter::and`].
+	pub fn or(self, other: Filter<'a>) -> Self {
+		self.combine(WherePart::Or, other)
+	}
+
+	fn combine(mut self, op: WherePart, mut other: Filter<'a>) -> Self {
+		let a = self.split_params();
+		let b = other.split_params();
+
+		let whr = Where::combine(self.whr, op, other.whr);
+
+		let mut params = Params::new();
+		if !whr.is_empty() {
+			params.append(a.whr);
+			params.append(b.whr);
+		}
+		params.append(a.order_by);
+		params.append(b.order_by);
+
+		let (limit, limit_params) = match self.limit {
+			Limit::All => (other.limit, b.limit),
+			limit => (limit, a.limit),
+		};
+		params.append(limit_params);
+
+		let (offset, offset_params) = match self.offset {
+			Offset::Zero => (other.offset, b.offset),
+			offset => (offset, a.offset),
+		};
+		params.append(offset_params);
+
+		self.order_by.inner.append(&mut other.order_by.inner);
+
+		Self {
+			whr,
+			order_by: self.order_by,
+			limit,
+			offset,
+			params,
+		}
+	}
+
+	/// Splits the params in the order they get used
+	pub(crate) fn split_params(&mut self) -> FilterParams<'a> {
+		let mut params = std::mem::replace(&mut self.params, Params::new());
+
+		let offset =
+			params.split_off(matches!(self.offset, Offset::Param) as usize);
+		let limit =
+			params.split_off(matches!(self.limit, Limit::Param) as usize);
+		let order_by = params.split_off(self.order_by.params_len());
+
+		FilterParams {
+			whr: params,
+			order_by,
+			limit,
+			offset,
+		}
+	}
+
 	pub(crate) fn to_formatter(&'a self) -> FilterFormatter<'a> {
 		FilterFormatter {
 			whr: &self.whr,
 			order_by: &self.order_by,
 			limit: &self.limit,
 			offset: &self.offset,
-			params: &self.params,
+			param_start: 0,
+		}
+	}
+}
+
+impl<'a> From<WhereFilter<'a>> for Filter<'a> {
+	fn from(filter: WhereFilter<'a>) -> Self {
+		Self {
+			whr: filter.whr,
+			params: filter.params,
+			..Self::new()
 		}
 	}
 }
 
+pub(crate) struct FilterParams<'a> {
+	pub whr: Params<'a>,
+	pub order_by: Params<'a>,
+	pub limit: Params<'a>,
+	pub offset: Params<'a>,
+}
+
 impl fmt::Display for Filter<'_> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f, "{}", self.to_formatter())
@@ -54,23 +157,40 @@ pub(crate) struct FilterFormatter<'a> {
 	pub order_by: &'a OrderBy,
 	pub limit: &'a Limit,
 	pub offset: &'a Offset,
-	pub params: &'a Params<'a>,
+	/// indexed by zero
+	pub param_start: usize,
 }
 
-impl fmt::Display for FilterFormatter<'_> {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		self.whr.fmt(f)?;
+impl FilterFormatter<'_> {
+	/// Returns the number of params the formatted filter uses
+	///
+	/// The params of the where and order come first, followed by the limit
+	/// and offset params.
+	pub fn params_len(&self) -> usize {
+		self.whr.params_len()
+			+ self.order_by.params_len()
+			+ matches!(self.limit, Limit::Param) as usize
+			+ matches!(self.offset, Offset::Param) as usize
+	}
 
-		self.order_by.fmt(f)?;
+	/// Returns the last param number which was used.
+	fn fmt_filter(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> Result<usize, fmt::Error> {
+		let mut whr = self.whr.to_formatter();
+		whr.param_start = self.param_start;
+		let param_num = whr.fmt_where(f)?;
 
-		let offset_has_param = matches!(self.offset, Offset::Param);
-		let param_count =
-			self.params.len() - if offset_has_param { 1 } else { 0 };
+		let mut order_by = self.order_by.to_formatter();
+		order_by.param_start = param_num;
+		let mut param_num = order_by.fmt_order_by(f)?;
 
 		match &self.limit {
 			Limit::Fixed(value) => write!(f, " LIMIT {}", value)?,
 			Limit::Param => {
-				write!(f, " LIMIT ${}", param_count)?;
+				param_num += 1;
+				write!(f, " LIMIT ${}", param_num)?;
 			}
 			Limit::All => {}
 		}
@@ -79,15 +199,132 @@ impl fmt::Display for FilterFormatter<'_> {
 			Offset::Zero => {}
 			Offset::Fixed(value) => write!(f, " OFFSET {}", value)?,
 			Offset::Param => {
-				write!(f, " OFFSET ${}", self.params.len())?;
+				param_num += 1;
+				write!(f, " OFFSET ${}", param_num)?;
 			}
 		}
 
-		Ok(())
+		Ok(param_num)
 	}
 }
 
-#[derive(Debug)]
+impl fmt::Display for FilterFormatter<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_filter(f).map(|_| ())
+	}
+}
+
+/// A select which can be used as a subquery or in a `WITH` clause
+///
+/// The params get appended to the query where the select is used.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Select<'a> {
+	pub query: SubQuery,
+	pub params: Params<'a>,
+}
+
+impl<'a> Select<'a> {
+	/// `SELECT * FROM "table"` with the given filter
+	pub fn new(table: impl Into<SqlStr>, filter: Filter<'a>) -> Self {
+		Self {
+			query: SubQuery {
+				columns: vec![],
+				table: table.into(),
+				whr: filter.whr,
+				order_by: filter.order_by,
+				limit: filter.limit,
+				offset: filter.offset,
+			},
+			params: filter.params,
+		}
+	}
+
+	/// Selects the column instead of `*`
+	pub fn column(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.query.columns.push(column.into());
+		self
+	}
+}
+
+/// The sql part of a [`Select`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SubQuery {
+	/// if empty `*` get's selected
+	pub columns: Vec<ColumnRef>,
+	pub table: SqlStr,
+	pub whr: Where,
+	pub order_by: OrderBy,
+	pub limit: Limit,
+	pub offset: Offset,
+}
+
+impl SubQuery {
+	fn params_len(&self) -> usize {
+		self.whr.params_len()
+			+ self.order_by.params_len()
+			+ matches!(self.limit, Limit::Param) as usize
+			+ matches!(self.offset, Offset::Param) as usize
+	}
+
+	pub(crate) fn to_formatter(&self) -> SubQueryFormatter<'_> {
+		SubQueryFormatter {
+			query: self,
+			param_start: 0,
+		}
+	}
+}
+
+pub(crate) struct SubQueryFormatter<'a> {
+	pub query: &'a SubQuery,
+	/// indexed by zero
+	pub param_start: usize,
+}
+
+impl SubQueryFormatter<'_> {
+	/// Writes `SELECT ... FROM ...` without parentheses
+	///
+	/// Returns the last param number which was used.
+	pub(crate) fn fmt_select(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> Result<usize, fmt::Error> {
+		let query = self.query;
+		f.write_str("SELECT ")?;
+
+		if query.columns.is_empty() {
+			f.write_str("*")?;
+		}
+
+		for (i, column) in query.columns.iter().enumerate() {
+			if i != 0 {
+				f.write_str(", ")?;
+			}
+
+			write!(f, "{}", column)?;
+		}
+
+		write!(f, " FROM {}", Ident::table(&query.table))?;
+
+		FilterFormatter {
+			whr: &query.whr,
+			order_by: &query.order_by,
+			limit: &query.limit,
+			offset: &query.offset,
+			param_start: self.param_start,
+		}
+		.fmt_filter(f)
+	}
+}
+
+impl fmt::Display for SubQueryFormatter<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_select(f).map(|_| ())
+	}
+}
+
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct WhereFilter<'a> {
 	pub whr: Where,
@@ -101,6 +338,16 @@ impl<'a> WhereFilter<'a> {
 			params: Params::new(),
 		}
 	}
+
+	/// Combines both filters with `AND`, an empty where is ignored
+	pub fn merge(mut self, other: WhereFilter<'a>) -> Self {
+		self.params.append(other.params);
+
+		Self {
+			whr: Where::combine(self.whr, WherePart::And, other.whr),
+			params: self.params,
+		}
+	}
 }
 
 impl fmt::Display for WhereFilter<'_> {
@@ -109,27 +356,109 @@ impl fmt::Display for WhereFilter<'_> {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Where {
 	inner: Vec<WherePart>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum WherePart {
 	Operation(WhereOperation),
 	And,
 	Or,
 	Nested(Where),
+	/// `NOT (...)`
+	Not(Where),
+	/// Raw sql, each `?` gets replaced with the next param number and `??`
+	/// gets written as `?`.
+	///
+	/// The params need to be pushed in the same order.
+	///
+	/// ## Note
+	/// Do not use untrusted input in the sql, this might lead to
+	/// SQL injection.
+	Raw(SqlStr),
+	/// `"column" IN (SELECT ...)`
+	///
+	/// The params of the select need to be appended at this position.
+	InSelect(ColumnRef, Box<SubQuery>),
+	/// `EXISTS (SELECT ...)`
+	///
+	/// The params of the select need to be appended at this position.
+	Exists(Box<SubQuery>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WhereOperation {
 	pub kind: Operator,
-	pub column: Cow<'static, str>,
+	pub column: ColumnRef,
 }
 
-#[derive(Debug)]
+/// A column with an optional table or alias qualifier
+///
+/// Get's written as `"table"."column"` or `"column"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnRef {
+	pub table: Option<Cow<'static, str>>,
+	pub name: Cow<'static, str>,
+}
+
+impl ColumnRef {
+	pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+		Self {
+			table: None,
+			name: name.into(),
+		}
+	}
+
+	pub fn qualified(
+		table: impl Into<Cow<'static, str>>,
+		name: impl Into<Cow<'static, str>>,
+	) -> Self {
+		Self {
+			table: Some(table.into()),
+			name: name.into(),
+		}
+	}
+}
+
+impl From<&'static str> for ColumnRef {
+	fn from(name: &'static str) -> Self {
+		Self::new(name)
+	}
+}
+
+impl From<String> for ColumnRef {
+	fn from(name: String) -> Self {
+		Self::new(name)
+	}
+}
+
+impl From<Cow<'static, str>> for ColumnRef {
+	fn from(name: Cow<'static, str>) -> Self {
+		Self::new(name)
+	}
+}
+
+impl From<(&'static str, &'static str)> for ColumnRef {
+	fn from((table, name): (&'static str, &'static str)) -> Self {
+		Self::qualified(table, name)
+	}
+}
+
+impl fmt::Display for ColumnRef {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.table {
+			Some(table) => {
+				write!(f, "{}.{}", Ident::table(table), Ident::new(&self.name))
+			}
+			None => write!(f, "{}", Ident::new(&self.name)),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum Operator {
 	Eq,
@@ -139,7 +468,39 @@ pub enum Operator {
 	Gt,
 	Gte,
 	Like,
-	In { length: usize },
+	In {
+		length: usize,
+	},
+	/// `"column" = ANY($1)`
+	///
+	/// Like `In` but binds the whole list as a single array param which
+	/// keeps the statement the same for every list length.
+	InArray,
+	/// `$1 = ANY("column")`
+	Any,
+	/// `"column" @> $1`
+	Contains,
+	/// `"column" <@ $1`
+	ContainedBy,
+	/// `"column" && $1`
+	Overlaps,
+
+	// the json operators cast the column to jsonb so they also work with
+	// json columns
+	/// `"column" -> $1 {op} $2` the key is bound as text and the value as
+	/// json
+	JsonGet(Box<Operator>),
+	/// `"column" ->> $1 {op} $2` the key and the value are bound as text
+	JsonGetText(Box<Operator>),
+	/// `"column" @> $1`
+	JsonContains,
+	/// `"column" ? $1`
+	JsonHasKey,
+	/// `jsonb_path_exists("column", $1)`
+	JsonPathExists,
+
+	/// `"column" @@ to_tsquery($1)`
+	Search(TsQuery),
 
 	// rhs will be ignored
 	IsNull,
@@ -147,7 +508,7 @@ pub enum Operator {
 	IsNotNull,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum WhereIdent {
 	Param,
@@ -163,24 +524,123 @@ impl Where {
 		self.inner.push(part.into());
 	}
 
-	fn is_empty(&self) -> bool {
+	pub fn is_empty(&self) -> bool {
 		self.inner.is_empty()
 	}
 
+	/// `(a) op (b)`
+	///
+	/// An empty where matches every row, so with `AND` the other one is
+	/// returned and with `OR` an empty where.
+	fn combine(a: Where, op: WherePart, b: Where) -> Where {
+		if matches!(op, WherePart::Or) && (a.is_empty() || b.is_empty()) {
+			return Where::new();
+		}
+
+		if a.is_empty() {
+			return b;
+		}
+
+		if b.is_empty() {
+			return a;
+		}
+
+		Self {
+			inner: vec![WherePart::Nested(a), op, WherePart::Nested(b)],
+		}
+	}
+
+	/// Returns the number of params the conditions use
+	pub fn params_len(&self) -> usize {
+		self.inner
+			.iter()
+			.map(|part| match part {
+				WherePart::Operation(op) => op.kind.params_len(),
+				WherePart::And | WherePart::Or => 0,
+				WherePart::Nested(whr) | WherePart::Not(whr) => {
+					whr.params_len()
+				}
+				WherePart::Raw(sql) => raw_params_len(sql),
+				WherePart::InSelect(_, query) | WherePart::Exists(query) => {
+					query.params_len()
+				}
+			})
+			.sum()
+	}
+
 	pub(crate) fn to_formatter<'a>(&'a self) -> WhereFormatter<'a> {
 		WhereFormatter {
-			whr: &self,
+			whr: self,
 			param_start: 0,
 		}
 	}
 }
 
-impl<'a> fmt::Display for Where {
+impl fmt::Display for Where {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		self.to_formatter().fmt(f)
 	}
 }
 
+/// The name of a param needs to be static, columns with an owned name get
+/// the name `param`
+// the Cow is needed to know if the name is static
+#[allow(clippy::ptr_arg)]
+fn param_name(name: &Cow<'static, str>) -> &'static str {
+	match name {
+		Cow::Borrowed(name) => name,
+		Cow::Owned(_) => "param",
+	}
+}
+
+/// Returns the number of `?` in raw sql, `??` is an escaped `?`
+fn raw_params_len(sql: &str) -> usize {
+	sql.replace("??", "").matches('?').count()
+}
+
+/// Used by the `RAW` of [`filter!`](crate::filter!) and
+/// [`whr!`](crate::whr!)
+///
+/// ## Panics
+/// If the sql doesn't have exactly `values` params.
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_raw_params(sql: &str, values: usize) {
+	let params = raw_params_len(sql);
+	assert!(
+		params == values,
+		"RAW({sql:?}) has {params} params but {values} values were given"
+	);
+}
+
+/// Writes raw sql replacing each `?` with the next param number
+///
+/// Returns the last param number which was used.
+fn fmt_raw(
+	f: &mut fmt::Formatter<'_>,
+	sql: &str,
+	mut param_num: usize,
+) -> Result<usize, fmt::Error> {
+	let mut chars = sql.chars().peekable();
+	while let Some(c) = chars.next() {
+		if c != '?' {
+			f.write_char(c)?;
+			continue;
+		}
+
+		if chars.peek() == Some(&'?') {
+			chars.next();
+			f.write_char('?')?;
+			continue;
+		}
+
+		param_num += 1;
+		write!(f, "${}", param_num)?;
+	}
+
+	Ok(param_num)
+}
+
 pub(crate) struct WhereFormatter<'a> {
 	pub whr: &'a Where,
 	/// indexed by zero
@@ -199,16 +659,37 @@ impl<'a> WhereFormatter<'a> {
 			match part {
 				WherePart::And => f.write_str(" AND ")?,
 				WherePart::Or => f.write_str(" OR ")?,
-				WherePart::Nested(inner) => {
+				WherePart::Nested(inner) | WherePart::Not(inner) => {
+					if matches!(part, WherePart::Not(_)) {
+						f.write_str("NOT ")?;
+					}
+
 					let mut inner = inner.to_formatter();
 					inner.param_start = param_num;
 					f.write_str("(")?;
 					param_num = inner.fmt_inner(f)?;
 					f.write_str(")")?;
 				}
+				WherePart::Raw(sql) => {
+					param_num = fmt_raw(f, sql, param_num)?;
+				}
+				WherePart::InSelect(column, query) => {
+					let mut query = query.to_formatter();
+					query.param_start = param_num;
+					write!(f, "{} IN (", column)?;
+					param_num = query.fmt_select(f)?;
+					f.write_str(")")?;
+				}
+				WherePart::Exists(query) => {
+					let mut query = query.to_formatter();
+					query.param_start = param_num;
+					f.write_str("EXISTS (")?;
+					param_num = query.fmt_select(f)?;
+					f.write_str(")")?;
+				}
 				WherePart::Operation(op) => match &op.kind {
 					Operator::IsNull | Operator::IsNotNull => {
-						write!(f, "\"{}\" {}", op.column, op.kind.as_str())?;
+						write!(f, "{} {}", op.column, op.kind.as_str())?;
 					}
 					// handle in special if the length is zero
 					// in this case we wan't the query to always return no results
@@ -216,7 +697,7 @@ impl<'a> WhereFormatter<'a> {
 						write!(f, "1=0")?;
 					}
 					Operator::In { length } => {
-						write!(f, "\"{}\" IN (", op.column)?;
+						write!(f, "{} IN (", op.column)?;
 
 						for i in 0..*length {
 							if i != 0 {
@@ -229,12 +710,60 @@ impl<'a> WhereFormatter<'a> {
 
 						f.write_str(")")?;
 					}
+					Operator::InArray => {
+						param_num += 1;
+						write!(f, "{} = ANY(${})", op.column, param_num)?;
+					}
+					Operator::Any => {
+						param_num += 1;
+						write!(f, "${} = ANY({})", param_num, op.column)?;
+					}
+					Operator::JsonGet(o) | Operator::JsonGetText(o) => {
+						write!(
+							f,
+							"{}::jsonb {} ${} {} ${}",
+							op.column,
+							op.kind.as_str(),
+							param_num + 1,
+							o.as_str(),
+							param_num + 2
+						)?;
+						param_num += 2;
+					}
+					Operator::JsonPathExists => {
+						param_num += 1;
+						write!(
+							f,
+							"jsonb_path_exists({}::jsonb, ${}::text::jsonpath)",
+							op.column, param_num
+						)?;
+					}
+					Operator::Search(query) => {
+						param_num += 1;
+						write!(
+							f,
+							"{} @@ {}(${})",
+							op.column,
+							query.as_fn_str(),
+							param_num
+						)?;
+					}
+					Operator::JsonContains | Operator::JsonHasKey => {
+						param_num += 1;
+						write!(
+							f,
+							"{}::jsonb {} ${}",
+							op.column,
+							op.kind.as_str(),
+							param_num
+						)?;
+					}
 					o => {
 						param_num += 1;
 
 						write!(
 							f,
-							"\"{}\" {} ${}",
+							"{} {} ${}",
 							op.column,
 							o.as_str(),
 							param_num
@@ -248,17 +777,27 @@ impl<'a> WhereFormatter<'a> {
 	}
 }
 
-impl<'a> fmt::Display for WhereFormatter<'a> {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl<'a> WhereFormatter<'a> {
+	/// Writes " WHERE " and all conditions if there are any
+	///
+	/// Returns the last param number which was used.
+	fn fmt_where(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> Result<usize, fmt::Error> {
 		if self.whr.is_empty() {
-			return Ok(());
+			return Ok(self.param_start);
 		}
 
 		f.write_str(" WHERE ")?;
 
-		self.fmt_inner(f)?;
+		self.fmt_inner(f)
+	}
+}
 
-		Ok(())
+impl<'a> fmt::Display for WhereFormatter<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_where(f).map(|_| ())
 	}
 }
 
@@ -269,6 +808,15 @@ impl From<WhereOperation> for WherePart {
 }
 
 impl Operator {
+	fn params_len(&self) -> usize {
+		match self {
+			Self::IsNull | Self::IsNotNull => 0,
+			Self::In { length } => *length,
+			Self::JsonGet(_) | Self::JsonGetText(_) => 2,
+			_ => 1,
+		}
+	}
+
 	fn as_str(&self) -> &str {
 		match self {
 			Operator::Eq => "=",
@@ -279,21 +827,103 @@ impl Operator {
 			Operator::Gte => ">=",
 			Operator::Like => "LIKE",
 			Operator::In { .. } => "IN",
+			Operator::InArray | Operator::Any => "= ANY",
+			Operator::Contains => "@>",
+			Operator::ContainedBy => "<@",
+			Operator::Overlaps => "&&",
+			Operator::JsonGet(_) => "->",
+			Operator::JsonGetText(_) => "->>",
+			Operator::JsonContains => "@>",
+			Operator::JsonHasKey => "?",
+			Operator::JsonPathExists => "@?",
+			Operator::Search(_) => "@@",
 			Operator::IsNull => "IS NULL",
 			Operator::IsNotNull => "IS NOT NULL",
 		}
 	}
 }
 
-#[derive(Debug)]
+/// The function which is used to convert a text into a tsquery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsQuery {
+	/// `to_tsquery`, expects the tsquery syntax like `rust & postgres`
+	ToTsQuery,
+	/// `plainto_tsquery`, all words need to match
+	Plain,
+	/// `phraseto_tsquery`, the words need to match in order
+	Phrase,
+	/// `websearch_to_tsquery`, supports quotes, `or` and `-`
+	Websearch,
+}
+
+impl TsQuery {
+	fn as_fn_str(&self) -> &'static str {
+		match self {
+			Self::ToTsQuery => "to_tsquery",
+			Self::Plain => "plainto_tsquery",
+			Self::Phrase => "phraseto_tsquery",
+			Self::Websearch => "websearch_to_tsquery",
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
 pub struct OrderBy {
 	inner: Vec<OrderByPart>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum OrderByPart {
+	#[deprecated = "use OrderByPart::Column"]
 	Asc(Cow<'static, str>),
+	#[deprecated = "use OrderByPart::Column"]
 	Desc(Cow<'static, str>),
+	/// `ts_rank("column", to_tsquery($1))`
+	///
+	/// The query needs to be pushed to the params.
+	TsRank {
+		column: ColumnRef,
+		query: TsQuery,
+		desc: bool,
+	},
+	/// `"column" COLLATE "collation" ASC NULLS LAST`
+	///
+	/// The collation is quoted as one identifier, for example `en_US.utf8`.
+	Column {
+		column: ColumnRef,
+		collate: Option<SqlStr>,
+		desc: bool,
+		nulls: Option<Nulls>,
+	},
+	/// Raw sql expression like `lower("name")`, each `?` gets replaced with
+	/// the next param number and `??` gets written as `?`.
+	///
+	/// The params need to be pushed in the same order.
+	///
+	/// ## Note
+	/// Do not use untrusted input in the sql, this might lead to
+	/// SQL injection.
+	Raw {
+		sql: SqlStr,
+		desc: bool,
+		nulls: Option<Nulls>,
+	},
+}
+
+/// Where null values are placed, `NULLS FIRST` or `NULLS LAST`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nulls {
+	First,
+	Last,
+}
+
+impl Nulls {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Self::First => "NULLS FIRST",
+			Self::Last => "NULLS LAST",
+		}
+	}
 }
 
 impl OrderBy {
@@ -301,43 +931,175 @@ impl OrderBy {
 		Self { inner: vec![] }
 	}
 
-	pub fn push_asc(&mut self, column: impl Into<Cow<'static, str>>) {
-		self.inner.push(OrderByPart::Asc(column.into()));
+	/// The params of a [`OrderByPart::TsRank`] or [`OrderByPart::Raw`] need
+	/// to be pushed in the same order.
+	pub fn push(&mut self, part: OrderByPart) {
+		self.inner.push(part);
 	}
 
-	pub fn push_desc(&mut self, column: impl Into<Cow<'static, str>>) {
-		self.inner.push(OrderByPart::Desc(column.into()));
+	pub fn push_asc(&mut self, column: impl Into<ColumnRef>) {
+		self.inner.push(OrderByPart::Column {
+			column: column.into(),
+			collate: None,
+			desc: false,
+			nulls: None,
+		});
+	}
+
+	pub fn push_desc(&mut self, column: impl Into<ColumnRef>) {
+		self.inner.push(OrderByPart::Column {
+			column: column.into(),
+			collate: None,
+			desc: true,
+			nulls: None,
+		});
+	}
+
+	pub fn push_ts_rank_asc(
+		&mut self,
+		column: impl Into<ColumnRef>,
+		query: TsQuery,
+	) {
+		self.inner.push(OrderByPart::TsRank {
+			column: column.into(),
+			query,
+			desc: false,
+		});
+	}
+
+	pub fn push_ts_rank_desc(
+		&mut self,
+		column: impl Into<ColumnRef>,
+		query: TsQuery,
+	) {
+		self.inner.push(OrderByPart::TsRank {
+			column: column.into(),
+			query,
+			desc: true,
+		});
 	}
 
 	fn is_empty(&self) -> bool {
 		self.inner.is_empty()
 	}
+
+	/// Returns the number of params the order uses
+	pub fn params_len(&self) -> usize {
+		self.inner
+			.iter()
+			.map(|part| match part {
+				OrderByPart::TsRank { .. } => 1,
+				OrderByPart::Raw { sql, .. } => raw_params_len(sql),
+				_ => 0,
+			})
+			.sum()
+	}
+
+	pub(crate) fn to_formatter(&self) -> OrderByFormatter<'_> {
+		OrderByFormatter {
+			order_by: self,
+			param_start: 0,
+		}
+	}
 }
 
 impl fmt::Display for OrderBy {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		if self.is_empty() {
-			return Ok(());
+		self.to_formatter().fmt(f)
+	}
+}
+
+pub(crate) struct OrderByFormatter<'a> {
+	pub order_by: &'a OrderBy,
+	/// indexed by zero
+	pub param_start: usize,
+}
+
+impl OrderByFormatter<'_> {
+	/// Returns the last param number which was used.
+	fn fmt_order_by(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> Result<usize, fmt::Error> {
+		let mut param_num = self.param_start;
+
+		if self.order_by.is_empty() {
+			return Ok(param_num);
 		}
 
 		f.write_str(" ORDER BY ")?;
 
-		for (i, part) in self.inner.iter().enumerate() {
+		for (i, part) in self.order_by.inner.iter().enumerate() {
 			if i != 0 {
 				f.write_str(", ")?;
 			}
 
+			#[allow(deprecated)]
 			match part {
-				OrderByPart::Asc(column) => write!(f, "\"{}\" ASC", column)?,
-				OrderByPart::Desc(column) => write!(f, "\"{}\" DESC", column)?,
+				OrderByPart::Asc(column) => {
+					write!(f, "{} ASC", Ident::new(column))?
+				}
+				OrderByPart::Desc(column) => {
+					write!(f, "{} DESC", Ident::new(column))?
+				}
+				OrderByPart::TsRank {
+					column,
+					query,
+					desc,
+				} => {
+					param_num += 1;
+					write!(
+						f,
+						"ts_rank({}, {}(${})) {}",
+						column,
+						query.as_fn_str(),
+						param_num,
+						if *desc { "DESC" } else { "ASC" }
+					)?;
+				}
+				OrderByPart::Column {
+					column,
+					collate,
+					desc,
+					nulls,
+				} => {
+					write!(f, "{}", column)?;
+					if let Some(collate) = collate {
+						write!(f, " COLLATE {}", Ident::new(collate))?;
+					}
+					fmt_order_suffix(f, *desc, *nulls)?;
+				}
+				OrderByPart::Raw { sql, desc, nulls } => {
+					param_num = fmt_raw(f, sql, param_num)?;
+					fmt_order_suffix(f, *desc, *nulls)?;
+				}
 			}
 		}
 
-		Ok(())
+		Ok(param_num)
+	}
+}
+
+fn fmt_order_suffix(
+	f: &mut fmt::Formatter<'_>,
+	desc: bool,
+	nulls: Option<Nulls>,
+) -> fmt::Result {
+	f.write_str(if desc { " DESC" } else { " ASC" })?;
+	if let Some(nulls) = nulls {
+		write!(f, " {}", nulls.as_str())?;
 	}
+
+	Ok(())
 }
 
-#[derive(Debug)]
+impl fmt::Display for OrderByFormatter<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_order_by(f).map(|_| ())
+	}
+}
+
+#[derive(Debug, Clone)]
 pub enum Limit {
 	Fixed(usize),
 	Param,
@@ -358,7 +1120,7 @@ impl Limit {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Offset {
 	Zero,
 	Fixed(usize),
@@ -379,7 +1141,7 @@ impl Offset {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Params<'a> {
 	inner: Vec<Param<'a>>,
 }
@@ -393,6 +1155,20 @@ impl<'a> Params<'a> {
 		self.inner.push(param);
 	}
 
+	/// Appends all params from `other`
+	pub fn append(&mut self, mut other: Params<'a>) {
+		self.inner.append(&mut other.inner);
+	}
+
+	/// Splits off the last `n` params
+	fn split_off(&mut self, n: usize) -> Params<'a> {
+		debug_assert!(n <= self.inner.len(), "params and filter out of sync");
+
+		Self {
+			inner: self.inner.split_off(self.inner.len().saturating_sub(n)),
+		}
+	}
+
 	pub fn len(&self) -> usize {
 		self.inner.len()
 	}
@@ -401,6 +1177,10 @@ impl<'a> Params<'a> {
 		self.inner.is_empty()
 	}
 
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = &Param<'a>> {
+		self.inner.iter()
+	}
+
 	pub fn iter_to_sql(
 		&self,
 	) -> impl ExactSizeIterator<Item = &(dyn ToSql + Sync)> {
@@ -408,7 +1188,7 @@ impl<'a> Params<'a> {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct Param<'a> {
 	// todo is the name still needed?
@@ -431,12 +1211,12 @@ impl<'a> Param<'a> {
 
 	pub fn new_owned<T>(name: &'static str, data: T) -> Self
 	where
-		T: ParamData + ToSql + Send + Sync + 'static,
+		T: ParamData + ToSql + Send + Sync + 'a,
 	{
 		Self {
 			name,
 			is_null: data.is_null(),
-			data: CowParamData::Owned(Box::new(data)),
+			data: CowParamData::Owned(Arc::new(data)),
 		}
 	}
 
@@ -445,13 +1225,14 @@ impl<'a> Param<'a> {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CowParamData<'a> {
 	Borrowed(&'a (dyn ToSql + Send + Sync)),
-	Owned(Box<dyn ToSql + Send + Sync>),
+	Owned(Arc<dyn ToSql + Send + Sync + 'a>),
 }
 
 impl<'a> CowParamData<'a> {
+	#[allow(clippy::should_implement_trait)]
 	pub fn as_ref(&self) -> &(dyn ToSql + Sync) {
 		match self {
 			CowParamData::Borrowed(data) => *data,
@@ -533,6 +1314,21 @@ param_not_null!(
 	Timeout
 );
 
+#[cfg(feature = "json")]
+impl<T> ParamData for types::json::Json<T> {
+	fn is_null(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(feature = "json")]
+impl ParamData for serde_json::Value {
+	fn is_null(&self) -> bool {
+		// a json null is not an sql null
+		false
+	}
+}
+
 #[cfg(feature = "email")]
 impl ParamData for email_address::EmailAddress {
 	fn is_null(&self) -> bool {
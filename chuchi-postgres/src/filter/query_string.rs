@@ -0,0 +1,430 @@
+use std::error::Error as StdError;
+
+use bytes::BytesMut;
+use postgres_types::{IsNull, ToSql, Type};
+use types::time::{Date, DateTime};
+
+use super::{
+	param_name, ColumnRef, Filter, Limit, Offset, Operator, Param, ParamData,
+	TsQuery, WhereOperation, WherePart,
+};
+use crate::table::column::{Column, ColumnKind};
+use crate::table::{Info, TableTemplate};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum QueryStringError {
+	#[error("Unknown column {0}")]
+	UnknownColumn(String),
+
+	#[error("Unknown operator {0}")]
+	UnknownOperator(String),
+
+	#[error("Operator {operator} is not supported on column {column}")]
+	UnsupportedOperator { column: String, operator: String },
+
+	#[error("Invalid value {value:?} for column {column}")]
+	InvalidValue { column: String, value: String },
+
+	#[error("Invalid {key} {value:?}")]
+	InvalidNumber { key: &'static str, value: String },
+
+	#[error("Invalid percent encoding")]
+	Encoding,
+}
+
+impl<'a> Filter<'a> {
+	/// Parses a query string and validates the columns against the table
+	///
+	/// See [`parse_query_string`] for the syntax.
+	pub fn from_query_string<T>(query: &str) -> Result<Self, QueryStringError>
+	where
+		T: TableTemplate,
+	{
+		parse_query_string(&T::table_info(), query)
+	}
+}
+
+/// Parses a query string and validates the columns against the info
+///
+/// The syntax is similar to PostgREST:
+/// `?status=eq.open&age=gt.18&order=created.desc,id.asc&limit=20&offset=40`
+///
+/// Operators: `eq`, `neq`, `lt`, `lte`, `gt`, `gte`, `like` (`*` is a
+/// wildcard), `in.(a,b)`, `is.null`, `is.notnull` and `fts` for tsvector
+/// columns. All conditions are combined with `AND`.
+///
+/// `order`, `limit` and `offset` are reserved and can't be used as column
+/// names.
+pub fn parse_query_string<'a>(
+	info: &Info,
+	query: &str,
+) -> Result<Filter<'a>, QueryStringError> {
+	let mut filter = Filter::new();
+	let query = query.strip_prefix('?').unwrap_or(query);
+
+	for pair in query.split('&').filter(|p| !p.is_empty()) {
+		let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+		let key = decode(key)?;
+		let value = decode(value)?;
+
+		match key.as_str() {
+			"order" => parse_order(info, &mut filter, &value)?,
+			"limit" => {
+				filter.limit = Limit::Fixed(parse_number("limit", &value)?)
+			}
+			"offset" => {
+				filter.offset = Offset::Fixed(parse_number("offset", &value)?)
+			}
+			_ => {
+				let column = find_column(info, &key)?;
+
+				if !filter.whr.is_empty() {
+					filter.whr.push(WherePart::And);
+				}
+
+				parse_condition(&mut filter, column, &value)?;
+			}
+		}
+	}
+
+	Ok(filter)
+}
+
+fn find_column<'i>(
+	info: &'i Info,
+	name: &str,
+) -> Result<&'i Column, QueryStringError> {
+	info.data()
+		.iter()
+		.find(|c| c.name == name)
+		.ok_or_else(|| QueryStringError::UnknownColumn(name.to_string()))
+}
+
+fn parse_number(
+	key: &'static str,
+	value: &str,
+) -> Result<usize, QueryStringError> {
+	value.parse().map_err(|_| QueryStringError::InvalidNumber {
+		key,
+		value: value.to_string(),
+	})
+}
+
+fn parse_order(
+	info: &Info,
+	filter: &mut Filter<'_>,
+	value: &str,
+) -> Result<(), QueryStringError> {
+	for part in value.split(',').filter(|p| !p.is_empty()) {
+		let (name, dir) = part.split_once('.').unwrap_or((part, "asc"));
+		let column = find_column(info, name)?;
+
+		match dir {
+			"asc" => filter.order_by.push_asc(column.name.clone()),
+			"desc" => filter.order_by.push_desc(column.name.clone()),
+			dir => {
+				return Err(QueryStringError::UnknownOperator(dir.to_string()))
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn parse_condition(
+	filter: &mut Filter<'_>,
+	column: &Column,
+	value: &str,
+) -> Result<(), QueryStringError> {
+	let (op, value) = value.split_once('.').unwrap_or((value, ""));
+
+	let unsupported = || QueryStringError::UnsupportedOperator {
+		column: column.name.to_string(),
+		operator: op.to_string(),
+	};
+
+	let kind = match &column.kind {
+		ColumnKind::Option(kind) => kind,
+		kind => kind,
+	};
+	let is_text = matches!(
+		kind,
+		ColumnKind::Text | ColumnKind::Varchar(_) | ColumnKind::FixedText(_)
+	);
+
+	let operator = match op {
+		"eq" => Operator::Eq,
+		"neq" => Operator::Ne,
+		"lt" => Operator::Lt,
+		"lte" => Operator::Lte,
+		"gt" => Operator::Gt,
+		"gte" => Operator::Gte,
+		"like" if is_text => Operator::Like,
+		"fts" if matches!(kind, ColumnKind::TsVector) => {
+			Operator::Search(TsQuery::Websearch)
+		}
+		"is" => match value {
+			"null" => Operator::IsNull,
+			"notnull" => Operator::IsNotNull,
+			_ => return Err(invalid_value(column, value)),
+		},
+		"in" => {
+			let list = value
+				.strip_prefix('(')
+				.and_then(|v| v.strip_suffix(')'))
+				.ok_or_else(|| invalid_value(column, value))?;
+
+			let mut length = 0;
+			for value in list.split(',').filter(|v| !v.is_empty()) {
+				let value = parse_value(column, kind, value)
+					.ok_or_else(unsupported)??;
+				filter
+					.params
+					.push(Param::new_owned(param_name(&column.name), value));
+				length += 1;
+			}
+
+			filter.whr.push(WhereOperation {
+				kind: Operator::In { length },
+				column: ColumnRef::new(column.name.clone()),
+			});
+
+			return Ok(());
+		}
+		"like" | "fts" => return Err(unsupported()),
+		op => return Err(QueryStringError::UnknownOperator(op.to_string())),
+	};
+
+	if !matches!(operator, Operator::IsNull | Operator::IsNotNull) {
+		let value = match operator {
+			Operator::Like => QueryValue::Text(value.replace('*', "%")),
+			Operator::Search(_) => QueryValue::Text(value.to_string()),
+			_ => parse_value(column, kind, value).ok_or_else(unsupported)??,
+		};
+
+		filter
+			.params
+			.push(Param::new_owned(param_name(&column.name), value));
+	}
+
+	filter.whr.push(WhereOperation {
+		kind: operator,
+		column: ColumnRef::new(column.name.clone()),
+	});
+
+	Ok(())
+}
+
+fn invalid_value(column: &Column, value: &str) -> QueryStringError {
+	QueryStringError::InvalidValue {
+		column: column.name.to_string(),
+		value: value.to_string(),
+	}
+}
+
+/// Returns None if the kind can't be compared
+fn parse_value(
+	column: &Column,
+	kind: &ColumnKind,
+	value: &str,
+) -> Option<Result<QueryValue, QueryStringError>> {
+	let value = match kind {
+		ColumnKind::Boolean => value
+			.parse()
+			.map(QueryValue::Bool)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::Text
+		| ColumnKind::Varchar(_)
+		| ColumnKind::FixedText(_) => Ok(QueryValue::Text(value.to_string())),
+		ColumnKind::Date => value
+			.parse()
+			.map(QueryValue::Date)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::Timestamp => DateTime::parse_from_iso8601(value)
+			.map(QueryValue::Timestamp)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::F64 => value
+			.parse()
+			.map(QueryValue::F64)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::F32 => value
+			.parse()
+			.map(QueryValue::F32)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::I64 => value
+			.parse()
+			.map(QueryValue::I64)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::I32 => value
+			.parse()
+			.map(QueryValue::I32)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::I16 => value
+			.parse()
+			.map(QueryValue::I16)
+			.map_err(|_| invalid_value(column, value)),
+		ColumnKind::Option(kind) => return parse_value(column, kind, value),
+		ColumnKind::TextArray
+		| ColumnKind::Bytea
+		| ColumnKind::Json
+		| ColumnKind::TsVector => return None,
+	};
+
+	Some(value)
+}
+
+/// Decodes percent encoding and `+` as a space
+fn decode(s: &str) -> Result<String, QueryStringError> {
+	let mut bytes = Vec::with_capacity(s.len());
+	let mut iter = s.bytes();
+
+	while let Some(b) = iter.next() {
+		match b {
+			b'+' => bytes.push(b' '),
+			b'%' => {
+				let hex = [
+					iter.next().ok_or(QueryStringError::Encoding)?,
+					iter.next().ok_or(QueryStringError::Encoding)?,
+				];
+				let hex = std::str::from_utf8(&hex)
+					.map_err(|_| QueryStringError::Encoding)?;
+				bytes.push(
+					u8::from_str_radix(hex, 16)
+						.map_err(|_| QueryStringError::Encoding)?,
+				);
+			}
+			b => bytes.push(b),
+		}
+	}
+
+	String::from_utf8(bytes).map_err(|_| QueryStringError::Encoding)
+}
+
+/// A value parsed according to the kind of the column
+#[derive(Debug, Clone)]
+enum QueryValue {
+	Bool(bool),
+	Text(String),
+	Date(Date),
+	Timestamp(DateTime),
+	F64(f64),
+	F32(f32),
+	I64(i64),
+	I32(i32),
+	I16(i16),
+}
+
+impl ToSql for QueryValue {
+	fn to_sql(
+		&self,
+		ty: &Type,
+		out: &mut BytesMut,
+	) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+		match self {
+			Self::Bool(v) => v.to_sql(ty, out),
+			Self::Text(v) => v.to_sql(ty, out),
+			Self::Date(v) => v.to_sql(ty, out),
+			Self::Timestamp(v) => v.to_sql(ty, out),
+			Self::F64(v) => v.to_sql(ty, out),
+			Self::F32(v) => v.to_sql(ty, out),
+			Self::I64(v) => v.to_sql(ty, out),
+			Self::I32(v) => v.to_sql(ty, out),
+			Self::I16(v) => v.to_sql(ty, out),
+		}
+	}
+
+	// the check happens in to_sql_checked
+	fn accepts(_ty: &Type) -> bool {
+		true
+	}
+
+	fn to_sql_checked(
+		&self,
+		ty: &Type,
+		out: &mut BytesMut,
+	) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+		match self {
+			Self::Bool(v) => v.to_sql_checked(ty, out),
+			Self::Text(v) => v.to_sql_checked(ty, out),
+			Self::Date(v) => v.to_sql_checked(ty, out),
+			Self::Timestamp(v) => v.to_sql_checked(ty, out),
+			Self::F64(v) => v.to_sql_checked(ty, out),
+			Self::F32(v) => v.to_sql_checked(ty, out),
+			Self::I64(v) => v.to_sql_checked(ty, out),
+			Self::I32(v) => v.to_sql_checked(ty, out),
+			Self::I16(v) => v.to_sql_checked(ty, out),
+		}
+	}
+}
+
+impl ParamData for QueryValue {
+	fn is_null(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::table::column::IndexKind;
+
+	fn info() -> Info {
+		Info::new(vec![
+			Column::new::<String>("status", None, IndexKind::None),
+			Column::new::<Option<i32>>("age", None, IndexKind::None),
+			Column::new::<DateTime>("created", None, IndexKind::None),
+		])
+	}
+
+	#[test]
+	fn parse() {
+		let filter = parse_query_string(
+			&info(),
+			"?status=eq.open&age=gt.18&status=in.(a,b%20c)&age=is.null\
+			&order=created.desc&limit=20&offset=40",
+		)
+		.unwrap();
+
+		assert_eq!(
+			filter.to_string(),
+			" WHERE \"status\" = $1 AND \"age\" > $2 AND \
+			\"status\" IN ($3, $4) AND \"age\" IS NULL \
+			ORDER BY \"created\" DESC LIMIT 20 OFFSET 40"
+		);
+		assert_eq!(filter.params.len(), 4);
+	}
+
+	#[test]
+	fn errors() {
+		let err = |q| parse_query_string(&info(), q).unwrap_err();
+
+		assert_eq!(
+			err("password=eq.1"),
+			QueryStringError::UnknownColumn("password".into())
+		);
+		assert_eq!(
+			err("age=gt.old"),
+			QueryStringError::InvalidValue {
+				column: "age".into(),
+				value: "old".into()
+			}
+		);
+		assert_eq!(
+			err("age=like.1*"),
+			QueryStringError::UnsupportedOperator {
+				column: "age".into(),
+				operator: "like".into()
+			}
+		);
+		assert_eq!(
+			err("age=between.1"),
+			QueryStringError::UnknownOperator("between".into())
+		);
+		assert_eq!(
+			err("order=created;drop"),
+			QueryStringError::UnknownColumn("created;drop".into())
+		);
+	}
+}
@@ -1,8 +1,33 @@
 /// Possible operators
 /// - = | != | < | <= | > | >= | LIKE | IN
-/// - AND | OR
+/// - = ANY | ANY | @> | <@ | &&
+/// - AND | OR | NOT (...)
+/// - RAW("lower(\"email\") = ?", &email) where each `?` is a param, panics
+///   if the number of `?` and values differ
+/// - IN SELECT(select) | EXISTS(select) with a [`Select`](crate::filter::Select)
 ///
 /// ~ | ~= | =~ are shortcuts for LIKE
+///
+/// Columns can be qualified with a table or alias `"u"."name" = &name`.
+///
+/// `"id" = ANY &ids` binds the list as one array param (`"id" = ANY($1)`),
+/// `"tags" ANY &tag` checks if the array column contains the value
+/// (`$1 = ANY("tags")`).
+///
+/// With the `json` feature:
+/// - `"data" -> "key" = &v` compares a field with a json value
+/// - `"data" ->> "key" = &v` compares a field as text
+/// - `"data" JSON @> &v` | `"data" ? &key` | `"data" @? &path`
+///
+/// Full text search:
+/// - `"doc" @@ &q` uses `to_tsquery`
+/// - `"doc" @@ plain(&q)` | `phrase(&q)` | `websearch(&q)`
+///
+/// Ordering:
+/// - `ORDER "due" ASC NULLS LAST` | `NULLS FIRST`
+/// - `ORDER "name" COLLATE "C" DESC`
+/// - `ORDER RAW("lower(\"name\")") ASC` where each `?` is a param, panics
+///   if the number of `?` and values differ
 /// ## Example
 /// ```
 /// use chuchi_postgres::filter;
@@ -13,19 +38,33 @@
 ///
 /// assert_eq!(r#" WHERE "a" = $1 AND "b" != $2 OR "c" IS NULL ORDER BY "a" ASC, "b" DESC"#, query.to_string());
 /// ```
+///
+/// ## Ranking
+/// ```
+/// use chuchi_postgres::filter;
+/// let q = "rust postgres";
+/// let query = filter!(
+/// 	"doc" @@ websearch(&q) ORDER ts_rank("doc", websearch(&q)) DESC
+/// );
+///
+/// assert_eq!(
+/// 	r#" WHERE "doc" @@ websearch_to_tsquery($1) ORDER BY ts_rank("doc", websearch_to_tsquery($2)) DESC"#,
+/// 	query.to_string()
+/// );
+/// ```
 #[macro_export]
 macro_rules! filter {
 	// order
-	(cont; $f:ident, ORDER $value:tt $($tt:tt)*) => ({
-		$crate::filter_order!($f, $value);
+	(cont; $f:ident, ORDER $($tt:tt)+) => ({
+		$crate::filter_order!($f, $($tt)+);
 	});
 	// limit
-	(cont; $f:ident, LIMIT $value:tt $($tt:tt)*) => ({
-		$crate::filter_limit!($f, $value);
+	(cont; $f:ident, LIMIT $($tt:tt)+) => ({
+		$crate::filter_limit!($f, $($tt)+);
 	});
 	// offset
-	(cont; $f:ident, OFFSET $value:tt $($tt:tt)*) => ({
-		$crate::filter_offset!($f, $value);
+	(cont; $f:ident, OFFSET $($tt:tt)+) => ({
+		$crate::filter_offset!($f, $($tt)+);
 	});
 	(cont; $f:ident, $($tt:tt)*) => ({
 		$crate::filter_inner!($f, $($tt)*);
@@ -42,9 +81,28 @@ macro_rules! filter {
 
 /// Possible operators
 /// - = | != | < | <= | > | >= | LIKE | IN
-/// - AND | OR
+/// - = ANY | ANY | @> | <@ | &&
+/// - AND | OR | NOT (...)
+/// - RAW("lower(\"email\") = ?", &email) where each `?` is a param, panics
+///   if the number of `?` and values differ
+/// - IN SELECT(select) | EXISTS(select) with a [`Select`](crate::filter::Select)
 ///
 /// ~ | ~= | =~ are shortcuts for LIKE
+///
+/// Columns can be qualified with a table or alias `"u"."name" = &name`.
+///
+/// `"id" = ANY &ids` binds the list as one array param (`"id" = ANY($1)`),
+/// `"tags" ANY &tag` checks if the array column contains the value
+/// (`$1 = ANY("tags")`).
+///
+/// With the `json` feature:
+/// - `"data" -> "key" = &v` compares a field with a json value
+/// - `"data" ->> "key" = &v` compares a field as text
+/// - `"data" JSON @> &v` | `"data" ? &key` | `"data" @? &path`
+///
+/// Full text search:
+/// - `"doc" @@ &q` uses `to_tsquery`
+/// - `"doc" @@ plain(&q)` | `phrase(&q)` | `websearch(&q)`
 /// ## Example
 /// ```
 /// use chuchi_postgres::whr;
@@ -81,88 +139,199 @@ macro_rules! filter_inner {
 		$crate::whr_log!($f, $($rest)*);
 	});
 
+	// not nested
+	($f:ident, NOT ($($tt:tt)+) $($rest:tt)*) => ({
+		let mut prev_where = std::mem::take(&mut $f.whr);
+		$crate::filter_inner!($f, $($tt)*);
+		std::mem::swap(&mut $f.whr, &mut prev_where);
+		$f.whr.push($crate::filter::WherePart::Not(prev_where));
+		$crate::whr_log!($f, $($rest)*);
+	});
+
+	// raw
+	($f:ident, RAW($sql:expr $(, $value:expr)* $(,)?) $($rest:tt)*) => (
+		let sql: $crate::filter::SqlStr = $sql.into();
+		let params_start = $f.params.len();
+		$(
+			$f.params.push($crate::filter::Param::new("raw", $value));
+		)*
+		$crate::filter::assert_raw_params(&sql, $f.params.len() - params_start);
+		$f.whr.push($crate::filter::WherePart::Raw(sql));
+		$crate::whr_log!($f, $($rest)*);
+	);
+
+	// exists
+	($f:ident, EXISTS($sub:expr) $($rest:tt)*) => (
+		let sub: $crate::filter::Select = $sub;
+		$f.whr.push($crate::filter::WherePart::Exists(Box::new(sub.query)));
+		$f.params.append(sub.params);
+		$crate::whr_log!($f, $($rest)*);
+	);
+
 	// reference ident eq
 	($f:ident, &$id:ident $($tt:tt)*) => (
-		$crate::whr_comp!($f, stringify!($id), Eq, &$id $($tt)*);
+		$crate::whr_comp!($f, (stringify!($id)), Eq, &$id $($tt)*);
 	);
 	// ident eq
 	($f:ident, $id:ident $($tt:tt)*) => (
-		$crate::whr_comp!($f, stringify!($id), Eq, $id $($tt)*);
+		$crate::whr_comp!($f, (stringify!($id)), Eq, $id $($tt)*);
+	);
+
+	// qualified column
+	($f:ident, $table:literal . $name:literal $($tt:tt)+) => (
+		$crate::whr_op!($f, ($table, $name) $($tt)*);
+	);
+	// column
+	($f:ident, $name:literal $($tt:tt)+) => (
+		$crate::whr_op!($f, ($name) $($tt)*);
+	);
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! whr_op {
+	// json get text
+	($f:ident, $col:tt ->> $key:literal $($tt:tt)+) => (
+		$crate::whr_json!($f, $col, $key, JsonGetText, $($tt)*);
+	);
+	// json get
+	($f:ident, $col:tt -> $key:literal $($tt:tt)+) => (
+		$crate::whr_json!($f, $col, $key, JsonGet, $($tt)*);
+	);
+	// json contains
+	($f:ident, $col:tt JSON @> $($tt:tt)+) => (
+		$crate::whr_comp_json!($f, $col, JsonContains, $($tt)*);
+	);
+	// json has key
+	($f:ident, $col:tt ? $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, JsonHasKey, $($tt)*);
+	);
+	// json path exists
+	($f:ident, $col:tt @? $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, JsonPathExists, $($tt)*);
+	);
+
+	// search
+	($f:ident, $col:tt @@ $($tt:tt)+) => (
+		$crate::whr_search!($f, $col, $($tt)*);
+	);
+
+	// eq any
+	($f:ident, $col:tt = ANY $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, InArray, $($tt)*);
+	);
+	// any
+	($f:ident, $col:tt ANY $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Any, $($tt)*);
+	);
+	// contains
+	($f:ident, $col:tt @> $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Contains, $($tt)*);
+	);
+	// contained by
+	($f:ident, $col:tt <@ $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, ContainedBy, $($tt)*);
+	);
+	// overlaps
+	($f:ident, $col:tt && $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Overlaps, $($tt)*);
 	);
 
 	// eq
-	($f:ident, $name:literal = $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Eq, $($tt)*);
+	($f:ident, $col:tt = $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Eq, $($tt)*);
 	);
 	// ne
-	($f:ident, $name:literal != $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Ne, $($tt)*);
+	($f:ident, $col:tt != $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Ne, $($tt)*);
 	);
 	// lt
-	($f:ident, $name:literal < $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Lt, $($tt)*);
+	($f:ident, $col:tt < $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Lt, $($tt)*);
 	);
 	// lte
-	($f:ident, $name:literal <= $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Lte, $($tt)*);
+	($f:ident, $col:tt <= $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Lte, $($tt)*);
 	);
 	// gt
-	($f:ident, $name:literal > $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Gt, $($tt)*);
+	($f:ident, $col:tt > $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Gt, $($tt)*);
 	);
 	// gte
-	($f:ident, $name:literal >= $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Gte, $($tt)*);
+	($f:ident, $col:tt >= $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Gte, $($tt)*);
 	);
 	// like
-	($f:ident, $name:literal LIKE $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, Like, $($tt)*);
+	($f:ident, $col:tt LIKE $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, Like, $($tt)*);
 	);
 	// like %val%
-	($f:ident, $name:literal ~ $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, ~, $($tt)*);
+	($f:ident, $col:tt ~ $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, ~, $($tt)*);
 	);
 	// like %val
-	($f:ident, $name:literal ~= $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, ~=, $($tt)*);
+	($f:ident, $col:tt ~= $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, ~=, $($tt)*);
 	);
 	// like val%
-	($f:ident, $name:literal =~ $($tt:tt)+) => (
-		$crate::whr_comp!($f, $name, =~, $($tt)*);
+	($f:ident, $col:tt =~ $($tt:tt)+) => (
+		$crate::whr_comp!($f, $col, =~, $($tt)*);
+	);
+	// in select
+	($f:ident, $col:tt IN SELECT($sub:expr) $($tt:tt)*) => (
+		let sub: $crate::filter::Select = $sub;
+		$f.whr.push($crate::filter::WherePart::InSelect(
+			$crate::whr_column!($col),
+			Box::new(sub.query)
+		));
+		$f.params.append(sub.params);
+		$crate::whr_log!($f, $($tt)*);
 	);
 	// in
-	($f:ident, $name:literal IN $($tt:tt)+) => (
-		$crate::whr_comp_in!($f, $name, $($tt)*);
+	($f:ident, $col:tt IN $($tt:tt)+) => (
+		$crate::whr_comp_in!($f, $col, $($tt)*);
 	);
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! whr_comp {
-	($f:ident, $name:expr, $symb:tt, &$value:tt $($tt:tt)*) => (
-		$crate::whr_comp!(symb; $f, $name, $symb, &$value, $($tt)*);
+	($f:ident, $col:tt, $symb:tt, &$value:tt $($tt:tt)*) => (
+		$crate::whr_comp!(symb; $f, $col, $symb, &$value, $($tt)*);
 	);
-	($f:ident, $name:expr, $symb:tt, $value:tt $($tt:tt)*) => (
-		$crate::whr_comp!(symb; $f, $name, $symb, $value, $($tt)*);
+	($f:ident, $col:tt, $symb:tt, $value:tt $($tt:tt)*) => (
+		$crate::whr_comp!(symb; $f, $col, $symb, $value, $($tt)*);
 	);
 
-	(symb; $f:ident, $name:expr, ~, $value:expr, $($tt:tt)*) => (
-		let param = $crate::filter::Param::new_owned($name, format!("%{}%", $value));
-		$crate::whr_comp!(fin; $f, param, Like, $($tt)*);
+	(symb; $f:ident, $col:tt, ~, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new_owned(
+			$crate::whr_column!(name; $col),
+			format!("%{}%", $value)
+		);
+		$crate::whr_comp!(fin; $f, $col, param, Like, $($tt)*);
 	);
-	(symb; $f:ident, $name:expr, ~=, $value:expr, $($tt:tt)*) => (
-		let param = $crate::filter::Param::new_owned($name, format!("%{}", $value));
-		$crate::whr_comp!(fin; $f, param, Like, $($tt)*);
+	(symb; $f:ident, $col:tt, ~=, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new_owned(
+			$crate::whr_column!(name; $col),
+			format!("%{}", $value)
+		);
+		$crate::whr_comp!(fin; $f, $col, param, Like, $($tt)*);
 	);
-	(symb; $f:ident, $name:expr, =~, $value:expr, $($tt:tt)*) => (
-		let param = $crate::filter::Param::new_owned($name, format!("{}%", $value));
-		$crate::whr_comp!(fin; $f, param, Like, $($tt)*);
+	(symb; $f:ident, $col:tt, =~, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new_owned(
+			$crate::whr_column!(name; $col),
+			format!("{}%", $value)
+		);
+		$crate::whr_comp!(fin; $f, $col, param, Like, $($tt)*);
 	);
-	(symb; $f:ident, $name:expr, $symb:ident, $value:expr, $($tt:tt)*) => (
-		let param = $crate::filter::Param::new($name, $value);
-		$crate::whr_comp!(fin; $f, param, $symb, $($tt)*);
+	(symb; $f:ident, $col:tt, $symb:ident, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new(
+			$crate::whr_column!(name; $col),
+			$value
+		);
+		$crate::whr_comp!(fin; $f, $col, param, $symb, $($tt)*);
 	);
-	(fin; $f:ident, $param:expr, $symb:ident, $($tt:tt)*) => (
+	(fin; $f:ident, $col:tt, $param:expr, $symb:ident, $($tt:tt)*) => (
 		let symb = $crate::filter::Operator::$symb;
 
 		let mut cont = true;
@@ -173,14 +342,14 @@ macro_rules! whr_comp {
 				$crate::filter::Operator::Eq => {
 					$f.whr.push($crate::filter::WhereOperation {
 						kind: $crate::filter::Operator::IsNull,
-						column: $param.name.into()
+						column: $crate::whr_column!($col)
 					});
 					cont = false;
 				},
 				$crate::filter::Operator::Ne => {
 					$f.whr.push($crate::filter::WhereOperation {
 						kind: $crate::filter::Operator::IsNotNull,
-						column: $param.name.into()
+						column: $crate::whr_column!($col)
 					});
 					cont = false;
 				},
@@ -191,7 +360,7 @@ macro_rules! whr_comp {
 		if cont {
 			$f.whr.push($crate::filter::WhereOperation {
 				kind: symb,
-				column: $param.name.into()
+				column: $crate::whr_column!($col)
 			});
 			$f.params.push($param);
 		}
@@ -201,28 +370,151 @@ macro_rules! whr_comp {
 	// LIMIT
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! whr_json {
+	($f:ident, $col:tt, $key:expr, $kind:ident, = $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Eq, $($tt)*);
+	);
+	($f:ident, $col:tt, $key:expr, $kind:ident, != $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Ne, $($tt)*);
+	);
+	($f:ident, $col:tt, $key:expr, $kind:ident, < $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Lt, $($tt)*);
+	);
+	($f:ident, $col:tt, $key:expr, $kind:ident, <= $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Lte, $($tt)*);
+	);
+	($f:ident, $col:tt, $key:expr, $kind:ident, > $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Gt, $($tt)*);
+	);
+	($f:ident, $col:tt, $key:expr, $kind:ident, >= $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Gte, $($tt)*);
+	);
+	($f:ident, $col:tt, $key:expr, $kind:ident, LIKE $($tt:tt)+) => (
+		$crate::whr_json!(op; $f, $col, $key, $kind, Like, $($tt)*);
+	);
+
+	(op; $f:ident, $col:tt, $key:expr, $kind:ident, $symb:ident, &$value:tt $($tt:tt)*) => (
+		$crate::whr_json!(val; $f, $col, $key, $kind, $symb, &$value, $($tt)*);
+	);
+	(op; $f:ident, $col:tt, $key:expr, $kind:ident, $symb:ident, $value:tt $($tt:tt)*) => (
+		$crate::whr_json!(val; $f, $col, $key, $kind, $symb, $value, $($tt)*);
+	);
+
+	// -> compares with a json value
+	(val; $f:ident, $col:tt, $key:expr, JsonGet, $symb:ident, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new_owned(
+			$crate::whr_column!(name; $col),
+			$crate::json::Json($value)
+		);
+		$crate::whr_json!(fin; $f, $col, $key, JsonGet, $symb, param, $($tt)*);
+	);
+	// ->> compares with text
+	(val; $f:ident, $col:tt, $key:expr, JsonGetText, $symb:ident, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new(
+			$crate::whr_column!(name; $col),
+			$value
+		);
+		$crate::whr_json!(fin; $f, $col, $key, JsonGetText, $symb, param, $($tt)*);
+	);
+
+	(fin; $f:ident, $col:tt, $key:expr, $kind:ident, $symb:ident, $param:expr, $($tt:tt)*) => (
+		$f.whr.push($crate::filter::WhereOperation {
+			kind: $crate::filter::Operator::$kind(
+				Box::new($crate::filter::Operator::$symb)
+			),
+			column: $crate::whr_column!($col)
+		});
+		$f.params.push($crate::filter::Param::new_owned(
+			$crate::whr_column!(name; $col),
+			$key
+		));
+		$f.params.push($param);
+
+		$crate::whr_log!($f, $($tt)*);
+	);
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! whr_comp_json {
+	($f:ident, $col:tt, $symb:ident, &$value:tt $($tt:tt)*) => (
+		$crate::whr_comp_json!(val; $f, $col, $symb, &$value, $($tt)*);
+	);
+	($f:ident, $col:tt, $symb:ident, $value:tt $($tt:tt)*) => (
+		$crate::whr_comp_json!(val; $f, $col, $symb, $value, $($tt)*);
+	);
+
+	(val; $f:ident, $col:tt, $symb:ident, $value:expr, $($tt:tt)*) => (
+		let param = $crate::filter::Param::new_owned(
+			$crate::whr_column!(name; $col),
+			$crate::json::Json($value)
+		);
+		$crate::whr_comp!(fin; $f, $col, param, $symb, $($tt)*);
+	);
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! whr_search {
+	($f:ident, $col:tt, plain($($value:tt)+) $($tt:tt)*) => (
+		$crate::whr_search!(val; $f, $col, Plain, $($value)+, $($tt)*);
+	);
+	($f:ident, $col:tt, phrase($($value:tt)+) $($tt:tt)*) => (
+		$crate::whr_search!(val; $f, $col, Phrase, $($value)+, $($tt)*);
+	);
+	($f:ident, $col:tt, websearch($($value:tt)+) $($tt:tt)*) => (
+		$crate::whr_search!(val; $f, $col, Websearch, $($value)+, $($tt)*);
+	);
+	($f:ident, $col:tt, &$value:tt $($tt:tt)*) => (
+		$crate::whr_search!(val; $f, $col, ToTsQuery, &$value, $($tt)*);
+	);
+	($f:ident, $col:tt, $value:tt $($tt:tt)*) => (
+		$crate::whr_search!(val; $f, $col, ToTsQuery, $value, $($tt)*);
+	);
+
+	(val; $f:ident, $col:tt, $query:ident, $value:expr, $($tt:tt)*) => (
+		$f.whr.push($crate::filter::WhereOperation {
+			kind: $crate::filter::Operator::Search(
+				$crate::filter::TsQuery::$query
+			),
+			column: $crate::whr_column!($col)
+		});
+		$f.params.push($crate::filter::Param::new(
+			$crate::whr_column!(name; $col),
+			$value
+		));
+
+		$crate::whr_log!($f, $($tt)*);
+	);
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! whr_comp_in {
-	($f:ident, $name:expr, &$value:tt $($tt:tt)*) => (
-		$crate::whr_comp_in!(two; $f, $name, &$value, $($tt)*);
+	($f:ident, $col:tt, &$value:tt $($tt:tt)*) => (
+		$crate::whr_comp_in!(two; $f, $col, &$value, $($tt)*);
 	);
-	($f:ident, $name:expr, $value:tt $($tt:tt)*) => (
-		$crate::whr_comp_in!(two; $f, $name, $value, $($tt)*);
+	($f:ident, $col:tt, $value:tt $($tt:tt)*) => (
+		$crate::whr_comp_in!(two; $f, $col, $value, $($tt)*);
 	);
 
-	(two; $f:ident, $name:expr, $value:expr, $($tt:tt)*) => (
+	(two; $f:ident, $col:tt, $value:expr, $($tt:tt)*) => (
 		{
 			let mut c = 0;
 			for val in $value {
 				c += 1;
-				let param = $crate::filter::Param::new($name, val);
+				let param = $crate::filter::Param::new(
+					$crate::whr_column!(name; $col),
+					val
+				);
 				$f.params.push(param);
 			}
 
 			$f.whr.push($crate::filter::WhereOperation {
 				kind: $crate::filter::Operator::In { length: c },
-				column: $name.into()
+				column: $crate::whr_column!($col)
 			});
 		}
 
@@ -230,6 +522,25 @@ macro_rules! whr_comp_in {
 	);
 }
 
+/// Converts `("table", "column")` or `("column")` to a `ColumnRef` or with
+/// `name;` to the name of the column
+#[doc(hidden)]
+#[macro_export]
+macro_rules! whr_column {
+	(($name:expr)) => {
+		$crate::filter::ColumnRef::new($name)
+	};
+	(($table:expr, $name:expr)) => {
+		$crate::filter::ColumnRef::qualified($table, $name)
+	};
+	(name; ($name:expr)) => {
+		$name
+	};
+	(name; ($table:expr, $name:expr)) => {
+		$name
+	};
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! whr_log {
@@ -257,12 +568,122 @@ macro_rules! whr_log {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! filter_order {
-	($f:ident, $name:literal DESC $($tt:tt)*) => (
-		$f.order_by.push_desc($name);
+	($f:ident, $table:literal . $name:literal $($tt:tt)+) => (
+		$crate::filter_order!(dir; $f, ($table, $name), $($tt)+);
+	);
+	($f:ident, $name:literal $($tt:tt)+) => (
+		$crate::filter_order!(dir; $f, ($name), $($tt)+);
+	);
+	(dir; $f:ident, $col:tt, COLLATE $collate:literal $($tt:tt)+) => (
+		$crate::filter_order!(
+			dir_col; $f, $col, Some($collate.into()), $($tt)+
+		);
+	);
+	(dir; $f:ident, $col:tt, $($tt:tt)+) => (
+		$crate::filter_order!(dir_col; $f, $col, None, $($tt)+);
+	);
+	(dir_col; $f:ident, $col:tt, $collate:expr, DESC $($tt:tt)*) => (
+		$crate::filter_order!(
+			nulls; $f, Column [
+				column: $crate::whr_column!($col),
+				collate: $collate,
+			], true, $($tt)*
+		);
+	);
+	(dir_col; $f:ident, $col:tt, $collate:expr, ASC $($tt:tt)*) => (
+		$crate::filter_order!(
+			nulls; $f, Column [
+				column: $crate::whr_column!($col),
+				collate: $collate,
+			], false, $($tt)*
+		);
+	);
+	($f:ident, RAW($sql:expr $(, $value:expr)* $(,)?) $($tt:tt)+) => (
+		let sql: $crate::filter::SqlStr = $sql.into();
+		let params_start = $f.params.len();
+		$(
+			$f.params.push($crate::filter::Param::new("raw", $value));
+		)*
+		$crate::filter::assert_raw_params(&sql, $f.params.len() - params_start);
+		$crate::filter_order!(dir_raw; $f, sql, $($tt)+);
+	);
+	(dir_raw; $f:ident, $sql:expr, DESC $($tt:tt)*) => (
+		$crate::filter_order!(
+			nulls; $f, Raw [sql: $sql.into(),], true, $($tt)*
+		);
+	);
+	(dir_raw; $f:ident, $sql:expr, ASC $($tt:tt)*) => (
+		$crate::filter_order!(
+			nulls; $f, Raw [sql: $sql.into(),], false, $($tt)*
+		);
+	);
+	(nulls; $f:ident, $kind:ident [$($fields:tt)*], $desc:tt, NULLS FIRST $($tt:tt)*) => (
+		$crate::filter_order!(
+			push; $f, $kind [$($fields)*], $desc,
+			Some($crate::filter::Nulls::First), $($tt)*
+		);
+	);
+	(nulls; $f:ident, $kind:ident [$($fields:tt)*], $desc:tt, NULLS LAST $($tt:tt)*) => (
+		$crate::filter_order!(
+			push; $f, $kind [$($fields)*], $desc,
+			Some($crate::filter::Nulls::Last), $($tt)*
+		);
+	);
+	(nulls; $f:ident, $kind:ident [$($fields:tt)*], $desc:tt, $($tt:tt)*) => (
+		$crate::filter_order!(
+			push; $f, $kind [$($fields)*], $desc, None, $($tt)*
+		);
+	);
+	(push; $f:ident, $kind:ident [$($fields:tt)*], $desc:tt, $nulls:expr, $($tt:tt)*) => (
+		$f.order_by.push($crate::filter::OrderByPart::$kind {
+			$($fields)*
+			desc: $desc,
+			nulls: $nulls,
+		});
+		$crate::filter_order!($f, $($tt)*);
+	);
+	($f:ident, ts_rank($table:literal . $name:literal, $query:ident($($value:tt)+)) $($tt:tt)*) => (
+		$crate::filter_order!(
+			rank; $f, ($table, $name), $query, $($value)+, $($tt)*
+		);
+	);
+	($f:ident, ts_rank($name:literal, $query:ident($($value:tt)+)) $($tt:tt)*) => (
+		$crate::filter_order!(
+			rank; $f, ($name), $query, $($value)+, $($tt)*
+		);
+	);
+	(rank; $f:ident, $col:tt, plain, $value:expr, $($tt:tt)*) => (
+		$crate::filter_order!(rank_fin; $f, $col, Plain, $value, $($tt)*);
+	);
+	(rank; $f:ident, $col:tt, phrase, $value:expr, $($tt:tt)*) => (
+		$crate::filter_order!(rank_fin; $f, $col, Phrase, $value, $($tt)*);
+	);
+	(rank; $f:ident, $col:tt, websearch, $value:expr, $($tt:tt)*) => (
+		$crate::filter_order!(rank_fin; $f, $col, Websearch, $value, $($tt)*);
+	);
+	(rank; $f:ident, $col:tt, to_tsquery, $value:expr, $($tt:tt)*) => (
+		$crate::filter_order!(rank_fin; $f, $col, ToTsQuery, $value, $($tt)*);
+	);
+	(rank_fin; $f:ident, $col:tt, $query:ident, $value:expr, DESC $($tt:tt)*) => (
+		$f.order_by.push_ts_rank_desc(
+			$crate::whr_column!($col),
+			$crate::filter::TsQuery::$query
+		);
+		$f.params.push($crate::filter::Param::new(
+			$crate::whr_column!(name; $col),
+			$value
+		));
 		$crate::filter_order!($f, $($tt)*);
 	);
-	($f:ident, $name:literal ASC $($tt:tt)*) => (
-		$f.order_by.push_asc($name);
+	(rank_fin; $f:ident, $col:tt, $query:ident, $value:expr, ASC $($tt:tt)*) => (
+		$f.order_by.push_ts_rank_asc(
+			$crate::whr_column!($col),
+			$crate::filter::TsQuery::$query
+		);
+		$f.params.push($crate::filter::Param::new(
+			$crate::whr_column!(name; $col),
+			$value
+		));
 		$crate::filter_order!($f, $($tt)*);
 	);
 	($f:ident, LIMIT $($tt:tt)+) => (
@@ -318,7 +739,7 @@ macro_rules! filter_offset {
 		$crate::filter_offset!(next; $f, $($tt)*);
 	);
 
-	(val; $f:ident, $name:literal, $value:expr, $($tt:tt)*) => (
+	(val; $f:ident, $name:expr, $value:expr, $($tt:tt)*) => (
 		let param = $crate::filter::Param::new($name, $value);
 		$f.offset.set_param();
 		$f.params.push(param);
@@ -331,6 +752,7 @@ macro_rules! filter_offset {
 
 #[cfg(test)]
 mod tests {
+	use crate::filter::{Filter, Select};
 	use crate::UniqueId;
 
 	#[test]
@@ -354,6 +776,10 @@ mod tests {
 		let limit = 10;
 		let query = filter!(id LIMIT &limit);
 		assert_eq!(query.to_string(), " WHERE \"id\" = $1 LIMIT $2");
+
+		let offset = 20;
+		let query = filter!(id LIMIT &limit OFFSET &offset);
+		assert_eq!(query.to_string(), " WHERE \"id\" = $1 LIMIT $2 OFFSET $3");
 	}
 
 	#[test]
@@ -370,6 +796,250 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_not() {
+		let a = &UniqueId::new();
+		let b = &UniqueId::new();
+		let c = &UniqueId::new();
+		let query = filter!(
+			"c" = &c AND NOT ("a" = &a OR NOT ("b" = &b)) AND "c" != &c
+		);
+		assert_eq!(
+			query.to_string(),
+			r#" WHERE "c" = $1 AND NOT ("a" = $2 OR NOT ("b" = $3)) AND "c" != $4"#
+		);
+
+		let query = whr!(NOT(a));
+		assert_eq!(query.to_string(), r#" WHERE NOT ("a" = $1)"#);
+	}
+
+	#[test]
+	fn test_raw() {
+		let email = "info@chuchi.dev";
+		let id = &UniqueId::new();
+		let query = filter!(
+			id AND RAW("lower(\"email\") = ? AND \"data\" ?? 'a'", &email)
+			AND RAW("\"created\" > now() - interval '1 day'") AND "id" != &id
+		);
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"id\" = $1 AND lower(\"email\") = $2 AND \"data\" ? 'a' \
+			AND \"created\" > now() - interval '1 day' AND \"id\" != $3"
+		);
+		assert_eq!(query.params.len(), 3);
+	}
+
+	#[test]
+	#[should_panic(expected = "has 2 params but 1 values were given")]
+	fn test_raw_missing_value() {
+		let a = 1;
+		let _ = whr!(RAW("\"a\" = ? OR \"b\" = ?", &a));
+	}
+
+	#[test]
+	#[should_panic(expected = "has 0 params but 1 values were given")]
+	fn test_order_raw_extra_value() {
+		let a = 1;
+		let _ = filter!(ORDER RAW("\"a\" ?? 'k'", &a) ASC);
+	}
+
+	#[test]
+	fn test_order_nulls_and_raw() {
+		let a = 1;
+		let q = "rust";
+		let query = filter!(
+			"a" = &a
+			ORDER "due" ASC NULLS LAST "u"."name" COLLATE "de-CH" DESC
+			RAW("CASE WHEN \"name\" = ? THEN 0 ELSE 1 END", &q) ASC
+			ts_rank("doc", plain(&q)) DESC "id" DESC NULLS FIRST
+			LIMIT 10
+		);
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"a\" = $1 ORDER BY \"due\" ASC NULLS LAST, \
+			\"u\".\"name\" COLLATE \"de-CH\" DESC, \
+			CASE WHEN \"name\" = $2 THEN 0 ELSE 1 END ASC, \
+			ts_rank(\"doc\", plainto_tsquery($3)) DESC, \
+			\"id\" DESC NULLS FIRST LIMIT 10"
+		);
+		assert_eq!(query.params.len(), 3);
+		assert_eq!(query.order_by.params_len(), 2);
+
+		let query = filter!(ORDER "name" COLLATE "en_US.utf8" ASC);
+		assert_eq!(
+			query.to_string(),
+			" ORDER BY \"name\" COLLATE \"en_US.utf8\" ASC"
+		);
+	}
+
+	#[test]
+	fn test_qualified_columns() {
+		let name = "chuchi";
+		let ids = vec![UniqueId::new()];
+		let deleted: Option<bool> = None;
+		let query = filter!(
+			"u"."name" = &name AND "p"."id" IN &ids AND "u"."deleted" = &deleted
+			ORDER "u"."name" ASC "id" DESC
+		);
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"u\".\"name\" = $1 AND \"p\".\"id\" IN ($2) \
+			AND \"u\".\"deleted\" IS NULL \
+			ORDER BY \"u\".\"name\" ASC, \"id\" DESC"
+		);
+		assert_eq!(query.params.len(), 2);
+	}
+
+	#[test]
+	#[allow(deprecated)]
+	fn test_order_asc_desc_parts() {
+		use crate::filter::OrderByPart;
+
+		let mut query = filter!();
+		query.order_by.push(OrderByPart::Asc("name".into()));
+		query.order_by.push(OrderByPart::Desc("id".into()));
+		assert_eq!(query.to_string(), " ORDER BY \"name\" ASC, \"id\" DESC");
+	}
+
+	#[test]
+	fn test_array_operators() {
+		let ids = vec![UniqueId::new(), UniqueId::new()];
+		let tag = "rust";
+		let tags = vec!["a".to_string(), "b".to_string()];
+		let query = filter!("id" = ANY &ids AND "tags" ANY &tag);
+		assert_eq!(
+			query.to_string(),
+			r#" WHERE "id" = ANY($1) AND $2 = ANY("tags")"#
+		);
+		assert_eq!(query.params.len(), 2);
+
+		let query =
+			filter!("tags" @> &tags OR "tags" <@ &tags AND "tags" && &tags);
+		assert_eq!(
+			query.to_string(),
+			r#" WHERE "tags" @> $1 OR "tags" <@ $2 AND "tags" && $3"#
+		);
+	}
+
+	#[cfg(feature = "json")]
+	#[test]
+	fn test_json_operators() {
+		let status = "open";
+		let value = serde_json::json!({ "a": 1 });
+		let key = "a";
+		let path = "$.a ? (@ > 0)";
+		let query = filter!(
+			"data" -> "status" = &status AND "data" ->> "status" != &status
+		);
+		assert_eq!(
+			query.to_string(),
+			r#" WHERE "data"::jsonb -> $1 = $2 AND "data"::jsonb ->> $3 != $4"#
+		);
+		assert_eq!(query.params.len(), 4);
+
+		let query = filter!(
+			"data" JSON @> &value OR "data" ? &key AND "data" @? &path
+		);
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"data\"::jsonb @> $1 OR \"data\"::jsonb ? $2 \
+			AND jsonb_path_exists(\"data\"::jsonb, $3::text::jsonpath)"
+		);
+	}
+
+	#[test]
+	fn test_only_order() {
+		let q = "rust";
+		let query = filter!(ORDER ts_rank("doc", websearch(&q)) DESC LIMIT 10);
+		assert_eq!(
+			query.to_string(),
+			" ORDER BY ts_rank(\"doc\", websearch_to_tsquery($1)) DESC LIMIT 10"
+		);
+	}
+
+	#[test]
+	fn test_search() {
+		let q = "rust";
+		let id = &UniqueId::new();
+		let limit = 10;
+		let query = filter!(
+			"doc" @@ &q AND "doc" @@ plain(&q) AND id
+			ORDER ts_rank("doc", plain(&q)) DESC "id" ASC
+			LIMIT &limit
+		);
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"doc\" @@ to_tsquery($1) AND \
+			\"doc\" @@ plainto_tsquery($2) AND \"id\" = $3 \
+			ORDER BY ts_rank(\"doc\", plainto_tsquery($4)) DESC, \"id\" ASC \
+			LIMIT $5"
+		);
+	}
+
+	#[test]
+	fn subqueries() {
+		let name = "Alice";
+		let min = 3;
+		let limit = 10;
+		let authors =
+			Select::new("users", filter!("name" = &name)).column("id");
+		let query = filter!(
+			"likes" > &min
+			AND "user_id" IN SELECT(authors)
+			AND NOT (EXISTS(Select::new("bans", filter!("reason" = &name))))
+			LIMIT &limit
+		);
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"likes\" > $1 AND \"user_id\" IN \
+			(SELECT \"id\" FROM \"users\" WHERE \"name\" = $2) AND \
+			NOT (EXISTS (SELECT * FROM \"bans\" WHERE \"reason\" = $3)) \
+			LIMIT $4"
+		);
+		assert_eq!(query.params.len(), 4);
+	}
+
+	#[test]
+	fn combine() {
+		let a = 1;
+		let b = 2;
+		let q = "rust";
+		let limit = 10;
+		let first =
+			filter!("a" = &a ORDER ts_rank("doc", plain(&q)) DESC LIMIT &limit);
+		let second = filter!("b" = &b OR "c" = &a ORDER "b" ASC);
+
+		let reused = first.clone();
+		let query = first.and(second);
+		assert_eq!(
+			query.to_string(),
+			" WHERE (\"a\" = $1) AND (\"b\" = $2 OR \"c\" = $3) \
+			ORDER BY ts_rank(\"doc\", plainto_tsquery($4)) DESC, \"b\" ASC \
+			LIMIT $5"
+		);
+		let names: Vec<_> = query.params.iter().map(|p| p.name).collect();
+		assert_eq!(names, ["a", "b", "c", "doc", "limit"]);
+
+		// an empty where matches every row
+		let query = reused.clone().or(filter!());
+		assert_eq!(
+			query.to_string(),
+			" ORDER BY ts_rank(\"doc\", plainto_tsquery($1)) DESC LIMIT $2"
+		);
+		let names: Vec<_> = query.params.iter().map(|p| p.name).collect();
+		assert_eq!(names, ["doc", "limit"]);
+
+		let query = reused.and(filter!());
+		assert_eq!(
+			query.to_string(),
+			" WHERE \"a\" = $1 \
+			ORDER BY ts_rank(\"doc\", plainto_tsquery($2)) DESC LIMIT $3"
+		);
+
+		let query: Filter = whr!("a" = &a).merge(whr!("b" = &b)).into();
+		assert_eq!(query.to_string(), " WHERE (\"a\" = $1) AND (\"b\" = $2)");
+	}
+
 	// #[test]
 	// fn test_order() {
 	// 	let id = &UniqueId::new();
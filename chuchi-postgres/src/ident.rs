@@ -0,0 +1,6 @@
+//! Quoting of sql identifiers
+//!
+//! Every table, column or alias name which get's written into sql should go
+//! through [`Ident`].
+
+pub use types::ident::Ident;
@@ -0,0 +1,509 @@
+//! Keyset (cursor) pagination
+//!
+//! Instead of skipping rows with `OFFSET`, the next page starts after the
+//! last row of the previous page: `WHERE ("created", "id") < ($1, $2)`.
+//! This stays fast even for deep pages if there is an index on the columns.
+//!
+//! The ordered columns should be unique together and not null, for example
+//! `created DESC, id DESC`.
+//!
+//! ## Example
+//! ```ignore
+//! use chuchi_postgres::keyset::{Cursor, Keyset, KeysetPage};
+//! use chuchi_postgres::{filter, Connection, FromRow, UniqueId};
+//! use chuchi_postgres::time::DateTime;
+//!
+//! #[derive(Debug, FromRow)]
+//! struct Post {
+//! 	id: UniqueId,
+//! 	created: DateTime,
+//! 	title: String,
+//! }
+//!
+//! async fn posts(
+//! 	conn: Connection<'_>,
+//! 	cursor: Option<&Cursor>,
+//! ) -> chuchi_postgres::Result<KeysetPage<Post>> {
+//! 	let published = true;
+//!
+//! 	Keyset::new(20)
+//! 		.desc("created")
+//! 		.desc("id")
+//! 		.select(conn, "posts", filter!(&published), cursor)
+//! 		.await
+//! }
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+use base64::DecodeError;
+use bytes::BytesMut;
+use futures_util::TryStreamExt;
+use postgres_types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::filter::{ColumnRef, Filter, Limit, Offset, OrderBy, Param};
+use crate::filter::{ParamData, WherePart};
+use crate::row::{FromRowOwned, NamedColumns};
+use crate::{Connection, Error, Ident, Row};
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CursorError {
+	#[error("Base64 error {0}")]
+	Base64(#[from] DecodeError),
+
+	#[error("The cursor is malformed")]
+	Malformed,
+
+	#[error("The cursor has {found} values but the keyset {expected} columns")]
+	ColumnCount { expected: usize, found: usize },
+
+	#[error("The keyset has no columns")]
+	NoColumns,
+}
+
+/// The position after which the next page starts
+///
+/// Contains the values of the keyset columns of the last row, encoded in the
+/// postgres binary format. Use [`Cursor::to_b64`] to send it to a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+	values: Vec<RawValue>,
+}
+
+impl Cursor {
+	fn from_row(row: &Row, keyset: &Keyset) -> Result<Self, Error> {
+		keyset
+			.columns
+			.iter()
+			.map(|(column, _)| {
+				row.try_get(column.name.as_ref())
+					.map_err(|e| Error::Deserialize(e.into()))
+			})
+			.collect::<Result<_, _>>()
+			.map(|values| Self { values })
+	}
+
+	pub fn to_b64(&self) -> String {
+		let mut bytes = vec![];
+
+		for value in &self.values {
+			match &value.0 {
+				Some(v) => {
+					bytes.extend_from_slice(&(v.len() as i32).to_be_bytes());
+					bytes.extend_from_slice(v);
+				}
+				None => bytes.extend_from_slice(&(-1i32).to_be_bytes()),
+			}
+		}
+
+		URL_SAFE_NO_PAD.encode(bytes)
+	}
+
+	pub fn parse_from_b64<T>(b64: T) -> Result<Self, CursorError>
+	where
+		T: AsRef<[u8]>,
+	{
+		let bytes = URL_SAFE_NO_PAD.decode(b64)?;
+		let mut rest = bytes.as_slice();
+		let mut values = vec![];
+
+		while !rest.is_empty() {
+			if rest.len() < 4 {
+				return Err(CursorError::Malformed);
+			}
+
+			let (len, r) = rest.split_at(4);
+			let len = i32::from_be_bytes(len.try_into().unwrap());
+			rest = r;
+
+			if len < 0 {
+				values.push(RawValue(None));
+				continue;
+			}
+
+			if rest.len() < len as usize {
+				return Err(CursorError::Malformed);
+			}
+
+			let (v, r) = rest.split_at(len as usize);
+			values.push(RawValue(Some(v.to_vec())));
+			rest = r;
+		}
+
+		Ok(Self { values })
+	}
+}
+
+impl fmt::Display for Cursor {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.to_b64())
+	}
+}
+
+impl FromStr for Cursor {
+	type Err = CursorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::parse_from_b64(s)
+	}
+}
+
+impl Serialize for Cursor {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(&self.to_b64())
+	}
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let s: String = Deserialize::deserialize(deserializer)?;
+		s.parse().map_err(serde::de::Error::custom)
+	}
+}
+
+/// A value in the postgres binary format which accepts any type
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RawValue(Option<Vec<u8>>);
+
+impl<'a> FromSql<'a> for RawValue {
+	fn from_sql(
+		_ty: &Type,
+		raw: &'a [u8],
+	) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+		Ok(Self(Some(raw.to_vec())))
+	}
+
+	fn from_sql_null(
+		_ty: &Type,
+	) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+		Ok(Self(None))
+	}
+
+	fn accepts(_ty: &Type) -> bool {
+		true
+	}
+}
+
+impl ToSql for RawValue {
+	fn to_sql(
+		&self,
+		_ty: &Type,
+		out: &mut BytesMut,
+	) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+		match &self.0 {
+			Some(v) => {
+				out.extend_from_slice(v);
+				Ok(IsNull::No)
+			}
+			None => Ok(IsNull::Yes),
+		}
+	}
+
+	fn accepts(_ty: &Type) -> bool {
+		true
+	}
+
+	to_sql_checked!();
+}
+
+impl ParamData for RawValue {
+	fn is_null(&self) -> bool {
+		self.0.is_none()
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+	Asc,
+	Desc,
+}
+
+/// One page of rows
+#[derive(Debug)]
+pub struct KeysetPage<R> {
+	pub items: Vec<R>,
+	/// `None` if this is the last page
+	pub next_cursor: Option<Cursor>,
+}
+
+/// The ordered columns and the page size
+#[derive(Debug, Clone)]
+pub struct Keyset {
+	columns: Vec<(ColumnRef, Order)>,
+	limit: usize,
+}
+
+impl Keyset {
+	pub fn new(limit: usize) -> Self {
+		Self {
+			columns: vec![],
+			limit,
+		}
+	}
+
+	pub fn asc(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.columns.push((column.into(), Order::Asc));
+		self
+	}
+
+	pub fn desc(mut self, column: impl Into<ColumnRef>) -> Self {
+		self.columns.push((column.into(), Order::Desc));
+		self
+	}
+
+	/// Adds the keyset condition, the order and the limit to the filter
+	///
+	/// One more row than the limit get's selected to know if there is a
+	/// next page.
+	///
+	/// The order, limit and offset of the filter get replaced together with
+	/// their params.
+	pub fn apply(
+		&self,
+		filter: &mut Filter<'_>,
+		cursor: Option<&Cursor>,
+	) -> Result<(), CursorError> {
+		if self.columns.is_empty() {
+			return Err(CursorError::NoColumns);
+		}
+
+		if let Some(cursor) = cursor {
+			if cursor.values.len() != self.columns.len() {
+				return Err(CursorError::ColumnCount {
+					expected: self.columns.len(),
+					found: cursor.values.len(),
+				});
+			}
+		}
+
+		// only the params of the where are still used
+		filter.params = filter.split_params().whr;
+
+		if let Some(cursor) = cursor {
+			let prev = std::mem::take(&mut filter.whr);
+			if !prev.is_empty() {
+				filter.whr.push(WherePart::Nested(prev));
+				filter.whr.push(WherePart::And);
+			}
+
+			let (sql, values) = self.condition(cursor);
+			filter.whr.push(WherePart::Raw(sql.into()));
+			for value in values {
+				filter
+					.params
+					.push(Param::new_owned("keyset", value.clone()));
+			}
+		}
+
+		let mut order_by = OrderBy::new();
+		for (column, order) in &self.columns {
+			match order {
+				Order::Asc => order_by.push_asc(column.clone()),
+				Order::Desc => order_by.push_desc(column.clone()),
+			}
+		}
+		filter.order_by = order_by;
+		filter.limit = Limit::Fixed(self.limit + 1);
+		filter.offset = Offset::Zero;
+
+		Ok(())
+	}
+
+	/// Returns the sql for [`WherePart::Raw`] and the values in the order of
+	/// the `?`
+	///
+	/// If all columns have the same order a row comparison is used
+	/// `("a", "b") < (?, ?)` else it get's expanded to
+	/// `("a" > ? OR ("a" = ? AND "b" < ?))`.
+	fn condition<'a>(&self, cursor: &'a Cursor) -> (String, Vec<&'a RawValue>) {
+		// a ? in a column name would be replaced with a param
+		let column =
+			|i: usize| self.columns[i].0.to_string().replace('?', "??");
+		let op = |i: usize| match self.columns[i].1 {
+			Order::Asc => ">",
+			Order::Desc => "<",
+		};
+
+		let mut sql = String::from("(");
+		let mut values = vec![];
+
+		let same_order = self.columns.windows(2).all(|w| w[0].1 == w[1].1);
+		if same_order {
+			for i in 0..self.columns.len() {
+				if i != 0 {
+					sql.push_str(", ");
+				}
+				sql.push_str(&column(i));
+			}
+
+			write!(sql, ") {} (", op(0)).unwrap();
+			for (i, value) in cursor.values.iter().enumerate() {
+				if i != 0 {
+					sql.push_str(", ");
+				}
+				sql.push('?');
+				values.push(value);
+			}
+		} else {
+			for i in 0..self.columns.len() {
+				if i != 0 {
+					sql.push_str(" OR ");
+				}
+
+				sql.push('(');
+				for j in 0..i {
+					write!(sql, "{} = ? AND ", column(j)).unwrap();
+					values.push(&cursor.values[j]);
+				}
+				write!(sql, "{} {} ?)", column(i), op(i)).unwrap();
+				values.push(&cursor.values[i]);
+			}
+		}
+
+		sql.push(')');
+
+		(sql, values)
+	}
+
+	/// Selects one page from the table
+	///
+	/// The selected columns of `R` need to contain all keyset columns.
+	pub async fn select<R>(
+		&self,
+		conn: Connection<'_>,
+		table: impl Into<Ident<'_>>,
+		mut filter: Filter<'_>,
+		cursor: Option<&Cursor>,
+	) -> Result<KeysetPage<R>, Error>
+	where
+		R: FromRowOwned + NamedColumns,
+	{
+		let table: Ident = table.into();
+		self.apply(&mut filter, cursor)?;
+
+		let sql =
+			format!("SELECT {} FROM {}{}", R::select_columns(), table, filter);
+		let stmt = conn.prepare_cached(&sql).await?;
+
+		let mut rows: Vec<Row> = conn
+			.query_raw(&stmt, filter.params.iter_to_sql())
+			.await?
+			.try_collect()
+			.await?;
+
+		let next_cursor = if rows.len() > self.limit {
+			rows.truncate(self.limit);
+			rows.last()
+				.map(|row| Cursor::from_row(row, self))
+				.transpose()?
+		} else {
+			None
+		};
+
+		let items = rows
+			.into_iter()
+			.map(|row| R::from_row_owned(row).map_err(Error::Deserialize))
+			.collect::<Result<_, _>>()?;
+
+		Ok(KeysetPage { items, next_cursor })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::filter;
+
+	fn cursor(values: &[Option<&[u8]>]) -> Cursor {
+		Cursor {
+			values: values.iter().map(|v| RawValue(v.map(Vec::from))).collect(),
+		}
+	}
+
+	#[test]
+	fn cursor_b64() {
+		let cursor = cursor(&[Some(b"2024"), None, Some(b"")]);
+		let b64 = cursor.to_b64();
+		assert_eq!(Cursor::parse_from_b64(&b64).unwrap(), cursor);
+		assert!(Cursor::parse_from_b64("AAAA").is_err());
+	}
+
+	#[test]
+	fn same_order() {
+		let published = true;
+		let mut filter = filter!(&published);
+		Keyset::new(20)
+			.desc("created")
+			.desc("id")
+			.apply(&mut filter, Some(&cursor(&[Some(b"a"), Some(b"b")])))
+			.unwrap();
+
+		assert_eq!(
+			filter.to_string(),
+			" WHERE (\"published\" = $1) AND (\"created\", \"id\") < ($2, $3) \
+			ORDER BY \"created\" DESC, \"id\" DESC LIMIT 21"
+		);
+		assert_eq!(filter.params.len(), 3);
+	}
+
+	#[test]
+	fn mixed_order() {
+		let mut filter = filter!();
+		Keyset::new(10)
+			.asc("name")
+			.desc("id")
+			.apply(&mut filter, Some(&cursor(&[Some(b"a"), Some(b"b")])))
+			.unwrap();
+
+		assert_eq!(
+			filter.to_string(),
+			" WHERE ((\"name\" > $1) OR (\"name\" = $2 AND \"id\" < $3)) \
+			ORDER BY \"name\" ASC, \"id\" DESC LIMIT 11"
+		);
+		assert_eq!(filter.params.len(), 3);
+	}
+
+	#[test]
+	fn replaces_params() {
+		let published = true;
+		let q = "rust";
+		let limit = 5;
+		let offset = 10;
+		let mut filter = filter!(
+			&published ORDER ts_rank("doc", plain(&q)) DESC
+			LIMIT &limit OFFSET &offset
+		);
+		Keyset::new(20)
+			.desc("id")
+			.apply(&mut filter, Some(&cursor(&[Some(b"a")])))
+			.unwrap();
+
+		assert_eq!(
+			filter.to_string(),
+			" WHERE (\"published\" = $1) AND (\"id\") < ($2) \
+			ORDER BY \"id\" DESC LIMIT 21"
+		);
+		let names: Vec<_> = filter.params.iter().map(|p| p.name).collect();
+		assert_eq!(names, ["published", "keyset"]);
+	}
+
+	#[test]
+	fn no_columns() {
+		let mut filter = filter!();
+		let res = Keyset::new(20).apply(&mut filter, Some(&cursor(&[])));
+		assert!(matches!(res, Err(CursorError::NoColumns)));
+	}
+}
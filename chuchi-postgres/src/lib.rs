@@ -22,11 +22,22 @@ pub use types::json;
 pub use types::time;
 pub use types::uid::UniqueId;
 
+pub mod ident;
+pub use ident::Ident;
+
 pub mod filter;
 
+pub mod query;
+
+pub mod keyset;
+
+pub mod paginate;
+
 pub mod migrations;
 
-pub use chuchi_postgres_derive::{row, FromRow, TableTempl, ToRow};
+pub use chuchi_postgres_derive::{
+	embed_migrations, row, FromRow, TableTempl, ToRow,
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -4,13 +4,45 @@
 //!
 //! A migration is an sql script which can be executed on the database
 //! this script is only executed once and then stored in the database.
+//!
+//! With a [`MigrationSet`] each migration has a version, pending migrations
+//! run in version order and the checksum of the sql get's stored so changes
+//! to an already applied migration are detected.
+//!
+//! A directory of `{version}_{name}.sql` files can be embedded at compile
+//! time with [`embed_migrations!`](crate::embed_migrations).
+//!
+//! ```ignore
+//! let set = embed_migrations!("migrations");
+//! db.migrations().run_all(&mut db.get().await?, &set).await?;
+//! ```
+//!
+//! The same directory can be migrated without starting the application with
+//! the `chuchi-postgres` binary of the `cli` feature, for example
+//! `DATABASE_URL=postgres://... chuchi-postgres migrate up`.
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::{connection::ConnectionOwned, filter, table::Table, Error};
+use crate::connection::{Connection, ConnectionOwned};
+use crate::table::{table::TableWithConn, Table};
+use crate::{filter, whr, Error, Ident};
 
 use chuchi_postgres_derive::{row, FromRow};
-use tracing::debug;
+use sha2::{Digest, Sha256};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, Config as PgConfig, NoTls};
+use tracing::{debug, info, warn};
+use types::migrations::MigrationFile;
 use types::time::DateTime;
 
 #[derive(Debug, FromRow)]
@@ -18,70 +50,781 @@ pub struct ExecutedMigration {
 	datetime: DateTime,
 }
 
+#[derive(Debug, FromRow)]
+struct AppliedMigration {
+	name: String,
+	version: Option<i64>,
+	checksum: Option<String>,
+	down: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum MigrationError {
+	#[error(
+		"Migration {version} {name} was changed after it was applied, \
+		expected checksum {expected} found {found}"
+	)]
+	ChecksumMismatch {
+		version: i64,
+		name: String,
+		expected: String,
+		found: String,
+	},
+
+	#[error("Migration {version} {name} has no down script")]
+	MissingDown { version: i64, name: String },
+
+	#[error("Timed out waiting for the migration lock")]
+	LockTimeout,
+
+	/// Another process held the lock with [`MigrationLock::Skip`]
+	#[error("Migrations are locked by another process")]
+	Locked,
+
+	/// A statement of a migration which runs without a transaction failed
+	///
+	/// The statements before it stay applied and the migration is not
+	/// recorded, so it runs again the next time.
+	#[error(
+		"Statement {statement} of migration {version} {name} failed, \
+		the statements before it stay applied: {source}"
+	)]
+	NonTransactional {
+		version: i64,
+		name: String,
+		/// starts at 1
+		statement: usize,
+		source: Error,
+	},
+
+	#[error("Migration {version} {name} failed: {source}")]
+	Code {
+		version: i64,
+		name: String,
+		source: Box<dyn StdError + Send + Sync>,
+	},
+
+	#[error(transparent)]
+	Postgres(#[from] Error),
+}
+
+impl MigrationError {
+	fn lock(e: Error) -> Self {
+		match &e {
+			Error::Other(pg)
+				if pg.code() == Some(&SqlState::LOCK_NOT_AVAILABLE) =>
+			{
+				Self::LockTimeout
+			}
+			_ => Self::Postgres(e),
+		}
+	}
+}
+
+/// What happens if another process is running migrations
+///
+/// Migrations are guarded by a postgres advisory lock, so multiple replicas
+/// starting at the same time don't run the same migrations.
+///
+/// The lock is held by a separate connection which is kept for the next
+/// migrations, it get's closed if their future get's dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationLock {
+	/// Waits until the lock is released
+	///
+	/// If a timeout is set and reached, an error is returned.
+	Wait { timeout: Option<Duration> },
+	/// Skips running migrations if another process holds the lock
+	Skip,
+}
+
+impl Default for MigrationLock {
+	fn default() -> Self {
+		Self::Wait { timeout: None }
+	}
+}
+
+/// What [`Migrations::run_all`] did
+#[derive(Debug, Clone)]
+pub enum RunAll<'s> {
+	/// The migrations which were applied by this call, in the order they ran
+	Applied(Vec<&'s Migration>),
+	/// Another process held the lock with [`MigrationLock::Skip`]
+	Skipped,
+}
+
+/// What [`Migrations::add`] did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Add {
+	/// The migration was applied now or before
+	Applied,
+	/// Another process held the lock with [`MigrationLock::Skip`]
+	Skipped,
+}
+
+pub type MigrationFuture<'a> = Pin<
+	Box<
+		dyn Future<Output = Result<(), Box<dyn StdError + Send + Sync>>>
+			+ Send
+			+ 'a,
+	>,
+>;
+
+/// Rust code which runs as a migration
+pub type MigrationFn =
+	Arc<dyn for<'a> Fn(Connection<'a>) -> MigrationFuture<'a> + Send + Sync>;
+
+/// What a migration executes
+#[derive(Clone)]
+pub enum Script {
+	Sql(Cow<'static, str>),
+	/// Runs inside the migration transaction
+	Code(MigrationFn),
+}
+
+impl fmt::Debug for Script {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Sql(sql) => f.debug_tuple("Sql").field(sql).finish(),
+			Self::Code(_) => f.write_str("Code"),
+		}
+	}
+}
+
+/// A migration with a version
+#[derive(Debug, Clone)]
+pub struct Migration {
+	pub version: i64,
+	pub name: Cow<'static, str>,
+	pub script: Script,
+	/// Reverts the migration, used by
+	/// [`rollback_to`](Migrations::rollback_to)
+	pub down: Option<Cow<'static, str>>,
+	/// If false the statements run one by one without a transaction
+	pub transaction: bool,
+}
+
+impl Migration {
+	pub fn new(
+		version: i64,
+		name: impl Into<Cow<'static, str>>,
+		sql: impl Into<Cow<'static, str>>,
+	) -> Self {
+		Self {
+			version,
+			name: name.into(),
+			script: Script::Sql(sql.into()),
+			down: None,
+			transaction: true,
+		}
+	}
+
+	/// A migration which runs rust code, for example to backfill a column
+	///
+	/// The code can't be checksummed so changes are not detected.
+	///
+	/// ## Example
+	/// ```
+	/// use chuchi_postgres::migrations::Migration;
+	///
+	/// let migration = Migration::code(3, "backfill names", |conn| {
+	/// 	Box::pin(async move {
+	/// 		conn.execute("UPDATE users SET name = 'unknown'", &[]).await?;
+	/// 		Ok(())
+	/// 	})
+	/// });
+	/// ```
+	pub fn code<F>(
+		version: i64,
+		name: impl Into<Cow<'static, str>>,
+		f: F,
+	) -> Self
+	where
+		F: for<'a> Fn(Connection<'a>) -> MigrationFuture<'a>
+			+ Send
+			+ Sync
+			+ 'static,
+	{
+		Self {
+			version,
+			name: name.into(),
+			script: Script::Code(Arc::new(f)),
+			down: None,
+			transaction: true,
+		}
+	}
+
+	/// Returns the sql if this is not a code migration
+	pub fn sql(&self) -> Option<&str> {
+		match &self.script {
+			Script::Sql(sql) => Some(sql),
+			Script::Code(_) => None,
+		}
+	}
+
+	pub fn with_down(mut self, down: impl Into<Cow<'static, str>>) -> Self {
+		self.down = Some(down.into());
+		self
+	}
+
+	/// Runs the statements one by one without a transaction
+	///
+	/// This is needed for `CREATE INDEX CONCURRENTLY`,
+	/// `ALTER TYPE ... ADD VALUE` or `VACUUM`.
+	///
+	/// If a statement fails the statements before it stay applied and the
+	/// migration is not recorded, so it runs again the next time. The
+	/// statements should therefore be idempotent, for example with
+	/// `IF NOT EXISTS`. Note that a failed `CREATE INDEX CONCURRENTLY` leaves
+	/// an invalid index which needs to be dropped.
+	///
+	/// The down script still runs in a transaction.
+	pub fn without_transaction(mut self) -> Self {
+		self.transaction = false;
+		self
+	}
+
+	/// Returns the sha256 of the sql as hex or `None` for code migrations
+	pub fn checksum(&self) -> Option<String> {
+		let checksum = Sha256::digest(self.sql()?.as_bytes())
+			.iter()
+			.map(|b| format!("{b:02x}"))
+			.collect();
+
+		Some(checksum)
+	}
+
+	/// Executes the sql or the code
+	async fn execute(
+		&self,
+		conn: Connection<'_>,
+	) -> Result<(), MigrationError> {
+		match &self.script {
+			Script::Sql(sql) => Ok(conn.batch_execute(sql).await?),
+			Script::Code(f) => {
+				f(conn).await.map_err(|source| MigrationError::Code {
+					version: self.version,
+					name: self.name.to_string(),
+					source,
+				})
+			}
+		}
+	}
+}
+
+/// A set of migrations ordered by their version
+///
+/// ## Example
+/// ```
+/// use chuchi_postgres::migrations::MigrationSet;
+///
+/// let set = MigrationSet::new()
+/// 	.add(1, "create users", "CREATE TABLE users (id bigint);")
+/// 	.add(2, "add name", "ALTER TABLE users ADD COLUMN name text;");
+/// assert_eq!(set.iter().count(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MigrationSet {
+	inner: BTreeMap<i64, Migration>,
+}
+
+impl MigrationSet {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// ## Panics
+	/// if the version was already added
+	pub fn add(
+		self,
+		version: i64,
+		name: impl Into<Cow<'static, str>>,
+		sql: impl Into<Cow<'static, str>>,
+	) -> Self {
+		self.push(Migration::new(version, name, sql))
+	}
+
+	/// Adds a migration with a down script which reverts it
+	///
+	/// ## Panics
+	/// if the version was already added
+	pub fn add_with_down(
+		self,
+		version: i64,
+		name: impl Into<Cow<'static, str>>,
+		sql: impl Into<Cow<'static, str>>,
+		down: impl Into<Cow<'static, str>>,
+	) -> Self {
+		self.push(Migration::new(version, name, sql).with_down(down))
+	}
+
+	/// ## Panics
+	/// if the version was already added
+	pub fn push(mut self, migration: Migration) -> Self {
+		let version = migration.version;
+		let prev = self.inner.insert(version, migration);
+		assert!(prev.is_none(), "migration version {version} already exists");
+
+		self
+	}
+
+	/// Returns the migrations ordered by their version
+	pub fn iter(&self) -> impl Iterator<Item = &Migration> {
+		self.inner.values()
+	}
+
+	/// Reads a directory of migrations at runtime
+	///
+	/// Uses the same layout as [`embed_migrations!`](crate::embed_migrations),
+	/// `{version}_{name}.sql` with an optional `{version}_{name}.down.sql`
+	/// and a first line `-- no-transaction` to run without a transaction.
+	/// Files which don't end in `.sql` are ignored.
+	pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+		let mut ups = BTreeMap::new();
+		let mut downs = BTreeMap::new();
+
+		for entry in fs::read_dir(dir)? {
+			let path = entry?.path();
+			if !path.is_file() {
+				continue;
+			}
+
+			let Some(file_name) = path.file_name().and_then(|n| n.to_str())
+			else {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("invalid file name {}", path.display()),
+				));
+			};
+			let Some(file) = MigrationFile::parse(file_name) else {
+				continue;
+			};
+			let MigrationFile {
+				version,
+				name,
+				is_down,
+			} = file.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+			let name = name.to_string();
+
+			let map = match is_down {
+				true => &mut downs,
+				false => &mut ups,
+			};
+			let sql = fs::read_to_string(&path)?;
+			if let Some((prev, _)) = map.insert(version, (name.clone(), sql)) {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!(
+						"migration version {version} is used by {prev} and {name}"
+					),
+				));
+			}
+		}
+
+		let mut set = Self::new();
+		for (version, (name, sql)) in ups {
+			let no_transaction = sql
+				.lines()
+				.next()
+				.is_some_and(|l| l.trim() == "-- no-transaction");
+
+			let mut migration = Migration::new(version, name.clone(), sql);
+			match downs.remove(&version) {
+				Some((down_name, down)) if down_name == name => {
+					migration = migration.with_down(down);
+				}
+				Some((down_name, _)) => {
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!(
+							"migration version {version} is used by {name} \
+							and {down_name}"
+						),
+					))
+				}
+				None => {}
+			}
+			if no_transaction {
+				migration = migration.without_transaction();
+			}
+
+			set = set.push(migration);
+		}
+
+		if let Some((_, (name, _))) = downs.into_iter().next() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("migration {name} only has a down script"),
+			));
+		}
+
+		Ok(set)
+	}
+}
+
+/// A row of the migrations table
+#[derive(Debug, Clone, FromRow)]
+pub struct MigrationRecord {
+	pub name: String,
+	/// `None` if the migration was run with [`Migrations::add`]
+	pub version: Option<i64>,
+	pub datetime: DateTime,
+	pub checksum: Option<String>,
+	pub duration_ms: Option<i64>,
+}
+
+/// The state of a migration of a [`MigrationSet`]
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+	pub version: i64,
+	pub name: String,
+	/// The checksum of the sql in the code, `None` for code migrations
+	pub checksum: Option<String>,
+	/// `None` if the migration is pending
+	pub applied: Option<MigrationRecord>,
+}
+
+impl MigrationStatus {
+	pub fn is_applied(&self) -> bool {
+		self.applied.is_some()
+	}
+
+	/// Returns true if the sql changed after the migration was applied
+	pub fn is_changed(&self) -> bool {
+		self.applied
+			.as_ref()
+			.and_then(|r| r.checksum.as_ref())
+			.zip(self.checksum.as_ref())
+			.is_some_and(|(applied, checksum)| applied != checksum)
+	}
+}
+
+/// Returned by [`Migrations::status`]
+#[derive(Debug, Clone)]
+pub struct Status {
+	/// All migrations of the set ordered by their version
+	pub migrations: Vec<MigrationStatus>,
+	/// Migrations which exist in the database but not in the set
+	pub unknown: Vec<MigrationRecord>,
+}
+
+impl Status {
+	pub fn pending(&self) -> impl Iterator<Item = &MigrationStatus> {
+		self.migrations.iter().filter(|m| !m.is_applied())
+	}
+}
+
 /// Holds all migrations
 ///
 /// and checks which migrations already ran, and runs the others
 #[derive(Debug, Clone)]
 pub struct Migrations {
 	table: Table,
+	lock: MigrationLock,
+	pg_config: PgConfig,
+	/// the connection of the last lock, see [`Migrations::lock`]
+	lock_client: Arc<Mutex<Option<Client>>>,
 }
 
 impl Migrations {
 	/// Create a new Migrations
-	pub(super) fn new(table_name: Option<String>) -> Self {
+	pub(super) fn new(
+		table_name: Option<String>,
+		lock: MigrationLock,
+		pg_config: PgConfig,
+	) -> Self {
 		Self {
 			table: Table::new(
 				table_name.map(Cow::Owned).unwrap_or("migrations".into()),
 			),
+			lock,
+			pg_config,
+			lock_client: Arc::new(Mutex::new(None)),
 		}
 	}
 
 	pub(super) async fn init(
 		&self,
-		db: &mut ConnectionOwned,
-	) -> Result<(), Error> {
-		let db = db.transaction().await?;
-		let conn = db.connection();
+		conn: &mut ConnectionOwned,
+	) -> Result<(), MigrationError> {
+		let Some(lock) = self.lock().await.map_err(MigrationError::lock)?
+		else {
+			// the table still needs to exist, only upgrading it is skipped
+			conn.connection()
+				.batch_execute(&create_table_sql(
+					Ident::table(self.table.name()),
+					self.table.name(),
+				))
+				.await?;
+			return Ok(());
+		};
+
+		let res = async {
+			let db = conn.transaction().await?;
+			self.create_table(db.connection(), None).await?;
+			db.commit().await
+		}
+		.await;
+
+		self.unlock(lock).await;
+		res.map_err(Into::into)
+	}
+
+	/// Takes the advisory lock for the migrations table
+	///
+	/// Returns None if the lock is held by someone else and the migrations
+	/// should be skipped.
+	///
+	/// The lock is held by a separate connection, so it get's released when
+	/// the returned client is dropped, even if the caller is cancelled.
+	/// [`unlock`](Self::unlock) keeps the connection for the next lock.
+	async fn lock(&self) -> Result<Option<Client>, Error> {
+		let cached = self.lock_client.lock().unwrap().take();
+		let client = match cached {
+			Some(client) if !client.is_closed() => client,
+			_ => {
+				let (client, connection) =
+					self.pg_config.connect(NoTls).await?;
+				tokio::spawn(async move {
+					if let Err(e) = connection.await {
+						warn!("migration lock connection failed: {e}");
+					}
+				});
+				client
+			}
+		};
+
+		let key = self.lock_key();
+
+		let timeout = match self.lock {
+			MigrationLock::Skip => {
+				let locked: bool = client
+					.query_one(
+						"SELECT pg_try_advisory_lock(hashtext($1))",
+						&[&key],
+					)
+					.await?
+					.try_get(0)?;
+
+				if !locked {
+					info!("migrations are locked by another process, skipping");
+					*self.lock_client.lock().unwrap() = Some(client);
+					return Ok(None);
+				}
+
+				return Ok(Some(client));
+			}
+			MigrationLock::Wait { timeout } => timeout,
+		};
+
+		if let Some(timeout) = timeout {
+			client
+				.batch_execute(&format!(
+					"SET lock_timeout = {}",
+					timeout.as_millis().max(1)
+				))
+				.await?;
+		}
+
+		client
+			.execute("SELECT pg_advisory_lock(hashtext($1))", &[&key])
+			.await?;
+
+		Ok(Some(client))
+	}
+
+	/// Releases the lock and keeps the connection for the next lock
+	///
+	/// Errors are only logged since the connection get's closed, which
+	/// releases the lock anyway.
+	async fn unlock(&self, client: Client) {
+		let res = client
+			.execute(
+				"SELECT pg_advisory_unlock(hashtext($1))",
+				&[&self.lock_key()],
+			)
+			.await;
+
+		match res {
+			Ok(_) => *self.lock_client.lock().unwrap() = Some(client),
+			Err(e) => warn!("could not release the migration lock: {e}"),
+		}
+	}
+
+	fn lock_key(&self) -> String {
+		format!("chuchi-postgres migrations {}", self.table.name())
+	}
 
-		// replace migrations with the correct table name
-		let table_exists =
-			TABLE_EXISTS.replace("migrations", self.table.name());
+	/// Creates the migrations table if it does not exist
+	///
+	/// Without a schema the current schema is used.
+	async fn create_table(
+		&self,
+		conn: Connection<'_>,
+		schema: Option<&str>,
+	) -> Result<(), Error> {
+		let name = self.table.name();
 
 		// check if the migrations table exists
-		let [result] =
-			conn.query_one::<[bool; 1], _>(&table_exists, &[]).await?;
+		let [result] = conn
+			.query_one::<[bool; 1], _>(TABLE_EXISTS, &[&schema, &name])
+			.await?;
+
+		let table = match schema {
+			Some(schema) => Ident::with_schema(schema, name),
+			None => Ident::table(name),
+		};
 
 		if !result {
-			let create_table =
-				CREATE_TABLE.replace("migrations", self.table.name());
-			conn.batch_execute(&create_table).await?;
+			conn.batch_execute(&create_table_sql(table, name)).await?;
+
+			return Ok(());
 		}
 
-		db.commit().await?;
+		let primary_key = conn
+			.query_opt::<[String; 1], _>(NAME_PRIMARY_KEY, &[&schema, &name])
+			.await?;
+
+		// tables created before versions existed, versions can share a name
+		// so it's only unique for migrations run with add
+		if let Some([primary_key]) = primary_key {
+			conn.batch_execute(&format!(
+				"ALTER TABLE {table} \
+				ADD COLUMN IF NOT EXISTS version bigint UNIQUE, \
+				ADD COLUMN IF NOT EXISTS checksum text, \
+				ADD COLUMN IF NOT EXISTS duration_ms bigint, \
+				ADD COLUMN IF NOT EXISTS down text, \
+				DROP CONSTRAINT {}, \
+				ALTER COLUMN name SET NOT NULL; \
+				CREATE UNIQUE INDEX ON {table} (name) WHERE version IS NULL;",
+				Ident::new(&primary_key)
+			))
+			.await?;
+		}
 
 		Ok(())
 	}
 
+	/// Runs the migration if no migration with this name was added before
 	pub async fn add(
 		&self,
 		conn: &mut ConnectionOwned,
 		name: &str,
 		sql: &str,
+	) -> Result<Add, Error> {
+		let Some(lock) = self.lock().await? else {
+			return Ok(Add::Skipped);
+		};
+
+		let res = self.add_inner(conn, None, name, sql).await;
+
+		self.unlock(lock).await;
+		res.map(|_| Add::Applied)
+	}
+
+	/// Runs the migration in every schema
+	///
+	/// Each schema get's created if it does not exist and get's it's own
+	/// migrations table, the `search_path` is set to the schema while the
+	/// migration runs.
+	pub async fn add_to_schemas<I, S>(
+		&self,
+		conn: &mut ConnectionOwned,
+		schemas: I,
+		name: &str,
+		sql: &str,
+	) -> Result<Add, Error>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let Some(lock) = self.lock().await? else {
+			return Ok(Add::Skipped);
+		};
+
+		let res = async {
+			for schema in schemas {
+				self.add_inner(conn, Some(schema.as_ref()), name, sql)
+					.await?;
+			}
+
+			Ok(Add::Applied)
+		}
+		.await;
+
+		self.unlock(lock).await;
+		res
+	}
+
+	/// Sets the `search_path` to the schema and returns the migrations table
+	/// of it
+	async fn prepare_schema(
+		&self,
+		conn: Connection<'_>,
+		schema: Option<&str>,
+	) -> Result<Table, Error> {
+		let table = match schema {
+			Some(schema) => {
+				conn.batch_execute(&format!(
+					"CREATE SCHEMA IF NOT EXISTS {0}; \
+					SET LOCAL search_path TO {0}",
+					Ident::new(schema)
+				))
+				.await?;
+				self.create_table(conn, Some(schema)).await?;
+
+				Table::with_schema(
+					schema.to_string(),
+					self.table.name().to_string(),
+				)
+			}
+			None => self.table.clone(),
+		};
+
+		Ok(table)
+	}
+
+	/// Like [`prepare_schema`](Self::prepare_schema) but also locks the
+	/// migrations table, so only one process runs migrations at the same time
+	async fn prepare_locked(
+		&self,
+		conn: Connection<'_>,
+		schema: Option<&str>,
+	) -> Result<Table, Error> {
+		let table = self.prepare_schema(conn, schema).await?;
+		conn.batch_execute(&format!(
+			"LOCK TABLE {} IN SHARE ROW EXCLUSIVE MODE",
+			table.ident()
+		))
+		.await?;
+
+		Ok(table)
+	}
+
+	async fn add_inner(
+		&self,
+		conn: &mut ConnectionOwned,
+		schema: Option<&str>,
+		name: &str,
+		sql: &str,
 	) -> Result<(), Error> {
 		let trans = conn.transaction().await?;
 		let conn = trans.connection();
-		let table = self.table.with_conn(conn);
+
+		let table = self.prepare_schema(conn, schema).await?;
+		let table = table.with_conn(conn);
 
 		// check if the migration was already executed
 		let existing: Option<ExecutedMigration> =
-			table.select_opt(filter!(&name)).await?;
+			table.select_opt(filter!(&name LIMIT 1)).await?;
 		if let Some(mig) = existing {
 			debug!("migration {} was executed at {}", name, mig.datetime);
 			return Ok(());
 		}
 
 		// else execute it
-		conn.batch_execute(&sql).await?;
+		conn.batch_execute(sql).await?;
 
 		table
 			.insert(row! {
@@ -94,19 +837,616 @@ impl Migrations {
 
 		Ok(())
 	}
+
+	/// Runs all pending migrations in version order
+	///
+	/// Each migration runs in it's own transaction and get's stored with
+	/// the checksum of it's sql and how long it took.
+	///
+	/// Returns an error if the sql of an applied migration changed.
+	/// A migration which was run with [`add`](Self::add) under the same name
+	/// is treated as applied, without checking it's sql.
+	pub async fn run_all<'s>(
+		&self,
+		conn: &mut ConnectionOwned,
+		set: &'s MigrationSet,
+	) -> Result<RunAll<'s>, MigrationError> {
+		let Some(lock) = self.lock().await.map_err(MigrationError::lock)?
+		else {
+			return Ok(RunAll::Skipped);
+		};
+
+		let res = async {
+			let mut applied = vec![];
+			for migration in set.iter() {
+				if self.run_inner(conn, None, migration).await? {
+					applied.push(migration);
+				}
+			}
+
+			Ok(RunAll::Applied(applied))
+		}
+		.await;
+
+		self.unlock(lock).await;
+		res
+	}
+
+	/// Runs all pending migrations in every schema
+	///
+	/// See [`run_all`](Self::run_all) and
+	/// [`add_to_schemas`](Self::add_to_schemas), a migration is returned once
+	/// for every schema it was applied to.
+	pub async fn run_all_in_schemas<'s, I, S>(
+		&self,
+		conn: &mut ConnectionOwned,
+		schemas: I,
+		set: &'s MigrationSet,
+	) -> Result<RunAll<'s>, MigrationError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let Some(lock) = self.lock().await.map_err(MigrationError::lock)?
+		else {
+			return Ok(RunAll::Skipped);
+		};
+
+		let res = async {
+			let mut applied = vec![];
+			for schema in schemas {
+				for migration in set.iter() {
+					if self
+						.run_inner(conn, Some(schema.as_ref()), migration)
+						.await?
+					{
+						applied.push(migration);
+					}
+				}
+			}
+
+			Ok(RunAll::Applied(applied))
+		}
+		.await;
+
+		self.unlock(lock).await;
+		res
+	}
+
+	/// Returns true if the migration was applied
+	async fn run_inner(
+		&self,
+		conn: &mut ConnectionOwned,
+		schema: Option<&str>,
+		migration: &Migration,
+	) -> Result<bool, MigrationError> {
+		let trans = conn.transaction().await?;
+		let trans_conn = trans.connection();
+
+		let migrations_table = self.prepare_locked(trans_conn, schema).await?;
+		let table = migrations_table.with_conn(trans_conn);
+
+		let version = &migration.version;
+		let name = migration.name.as_ref();
+		let checksum = migration.checksum();
+
+		let no_version: Option<i64> = None;
+		let existing: Option<AppliedMigration> = table
+			.select_opt(filter!(
+				"version" = version OR ("name" = &name AND "version" = &no_version)
+				ORDER "version" ASC NULLS LAST
+				LIMIT 1
+			))
+			.await?;
+
+		let applied = match existing {
+			Some(AppliedMigration {
+				version: Some(_),
+				checksum: found,
+				name: applied_name,
+				down,
+			}) => {
+				if let Some((found, checksum)) = found.zip(checksum) {
+					if found != checksum {
+						return Err(MigrationError::ChecksumMismatch {
+							version: *version,
+							name: applied_name,
+							expected: found,
+							found: checksum,
+						});
+					}
+				}
+
+				if down.as_deref() == migration.down.as_deref() {
+					debug!("migration {version} {name} already applied");
+					return Ok(false);
+				}
+
+				// the down script can change without affecting the migration
+				let down = migration.down.as_deref();
+				table
+					.update(row! { down }, whr!("version" = version))
+					.await?;
+
+				false
+			}
+			// applied with add, which didn't store the sql so the checksum
+			// can't be verified and is left empty
+			Some(_) => {
+				warn!(
+					"migration {version} {name} was applied with add, \
+					it's checksum is not verified"
+				);
+
+				let down = migration.down.as_deref();
+				table
+					.update(
+						row! { "version": version, down },
+						whr!("name" = &name AND "version" = &no_version),
+					)
+					.await?;
+
+				false
+			}
+			None if migration.transaction => {
+				let start = Instant::now();
+				migration.execute(trans_conn).await?;
+
+				insert_record(table, migration, start).await?;
+
+				true
+			}
+			None => {
+				// the schema and table might have been created
+				trans.commit().await?;
+
+				self.run_without_transaction(
+					conn,
+					schema,
+					&migrations_table,
+					migration,
+				)
+				.await?;
+
+				return Ok(true);
+			}
+		};
+
+		trans.commit().await?;
+
+		Ok(applied)
+	}
+
+	async fn run_without_transaction(
+		&self,
+		conn: &ConnectionOwned,
+		schema: Option<&str>,
+		table: &Table,
+		migration: &Migration,
+	) -> Result<(), MigrationError> {
+		let conn = conn.connection();
+
+		// SET LOCAL from prepare_schema only applied to the transaction
+		if let Some(schema) = schema {
+			conn.batch_execute(&format!(
+				"SET search_path TO {}",
+				Ident::new(schema)
+			))
+			.await?;
+		}
+
+		let res = async {
+			let start = Instant::now();
+
+			let Some(sql) = migration.sql() else {
+				migration.execute(conn).await?;
+				insert_record(table.with_conn(conn), migration, start).await?;
+
+				return Ok(());
+			};
+
+			for (i, statement) in split_statements(sql).into_iter().enumerate()
+			{
+				conn.batch_execute(statement).await.map_err(|e| {
+					MigrationError::NonTransactional {
+						version: migration.version,
+						name: migration.name.to_string(),
+						statement: i + 1,
+						source: e,
+					}
+				})?;
+			}
+
+			insert_record(table.with_conn(conn), migration, start).await?;
+
+			Ok(())
+		}
+		.await;
+
+		if schema.is_some() {
+			conn.batch_execute("RESET search_path").await?;
+		}
+
+		res
+	}
+
+	/// Reverts all migrations with a version higher than `version`
+	///
+	/// The down scripts run in reverse order, each in it's own transaction,
+	/// and the migration get's removed from the migrations table.
+	///
+	/// Returns an error if a migration has no down script, the migrations
+	/// before it stay reverted, or [`MigrationError::Locked`] if another
+	/// process holds the lock with [`MigrationLock::Skip`].
+	pub async fn rollback_to(
+		&self,
+		conn: &mut ConnectionOwned,
+		version: i64,
+	) -> Result<(), MigrationError> {
+		let Some(lock) = self.lock().await.map_err(MigrationError::lock)?
+		else {
+			return Err(MigrationError::Locked);
+		};
+
+		let res = async {
+			while self.rollback_inner(conn, None, version).await? {}
+
+			Ok(())
+		}
+		.await;
+
+		self.unlock(lock).await;
+		res
+	}
+
+	/// Reverts the last `n` migrations
+	///
+	/// See [`rollback_to`](Self::rollback_to).
+	pub async fn rollback_last(
+		&self,
+		conn: &mut ConnectionOwned,
+		n: usize,
+	) -> Result<(), MigrationError> {
+		let Some(lock) = self.lock().await.map_err(MigrationError::lock)?
+		else {
+			return Err(MigrationError::Locked);
+		};
+
+		let res = async {
+			for _ in 0..n {
+				if !self.rollback_inner(conn, None, i64::MIN).await? {
+					break;
+				}
+			}
+
+			Ok(())
+		}
+		.await;
+
+		self.unlock(lock).await;
+		res
+	}
+
+	/// Reverts the last migration with a version higher than `version`
+	///
+	/// Returns false if there was no migration to revert.
+	async fn rollback_inner(
+		&self,
+		conn: &mut ConnectionOwned,
+		schema: Option<&str>,
+		version: i64,
+	) -> Result<bool, MigrationError> {
+		let trans = conn.transaction().await?;
+		let conn = trans.connection();
+
+		let table = self.prepare_locked(conn, schema).await?;
+		let table = table.with_conn(conn);
+
+		let last: Option<AppliedMigration> = table
+			.select_opt(filter!(
+				"version" > &version
+				ORDER "version" DESC
+				LIMIT 1
+			))
+			.await?;
+
+		let Some(AppliedMigration {
+			name,
+			version: Some(version),
+			down,
+			..
+		}) = last
+		else {
+			return Ok(false);
+		};
+
+		let Some(down) = down else {
+			return Err(MigrationError::MissingDown { version, name });
+		};
+
+		conn.batch_execute(&down).await?;
+		table.delete(whr!("version" = &version)).await?;
+
+		trans.commit().await?;
+
+		info!("migration {version} {name} reverted");
+
+		Ok(true)
+	}
+
+	/// Returns for every migration of the set if and when it was applied and
+	/// which migrations exist only in the database
+	pub async fn status(
+		&self,
+		conn: &ConnectionOwned,
+		set: &MigrationSet,
+	) -> Result<Status, MigrationError> {
+		let mut records = self.records(conn).await?;
+
+		let migrations = set
+			.iter()
+			.map(|migration| MigrationStatus {
+				version: migration.version,
+				name: migration.name.to_string(),
+				checksum: migration.checksum(),
+				applied: take_record(&mut records, migration),
+			})
+			.collect();
+
+		Ok(Status {
+			migrations,
+			unknown: records,
+		})
+	}
+
+	/// Returns the migrations [`run_all`](Self::run_all) would execute
+	/// without executing them
+	///
+	/// Like `run_all` it returns an error if the sql of an applied migration
+	/// changed.
+	///
+	/// ## Example
+	/// ```ignore
+	/// for migration in db.migrations().dry_run(&conn, &set).await? {
+	/// 	let sql = migration.sql().unwrap_or("-- rust code");
+	/// 	println!("-- {} {}\n{}", migration.version, migration.name, sql);
+	/// }
+	/// ```
+	pub async fn dry_run<'s>(
+		&self,
+		conn: &ConnectionOwned,
+		set: &'s MigrationSet,
+	) -> Result<Vec<&'s Migration>, MigrationError> {
+		let mut records = self.records(conn).await?;
+		let mut pending = vec![];
+
+		for migration in set.iter() {
+			let Some(record) = take_record(&mut records, migration) else {
+				pending.push(migration);
+				continue;
+			};
+
+			let checksum = migration.checksum();
+			match record.checksum.zip(checksum) {
+				Some((found, checksum)) if found != checksum => {
+					return Err(MigrationError::ChecksumMismatch {
+						version: migration.version,
+						name: record.name,
+						expected: found,
+						found: checksum,
+					});
+				}
+				_ => {}
+			}
+		}
+
+		Ok(pending)
+	}
+
+	async fn records(
+		&self,
+		conn: &ConnectionOwned,
+	) -> Result<Vec<MigrationRecord>, Error> {
+		self.table
+			.with_conn(conn.connection())
+			.select(filter!(ORDER "version" ASC NULLS FIRST "datetime" ASC))
+			.await
+	}
+}
+
+async fn insert_record(
+	table: TableWithConn<'_>,
+	migration: &Migration,
+	start: Instant,
+) -> Result<(), Error> {
+	let version = &migration.version;
+	let name = migration.name.as_ref();
+	let duration_ms = start.elapsed().as_millis() as i64;
+
+	info!("migration {version} {name} took {duration_ms}ms");
+
+	table
+		.insert(row! {
+			name,
+			"datetime": DateTime::now(),
+			"version": version,
+			"checksum": migration.checksum(),
+			"duration_ms": duration_ms,
+			"down": migration.down.as_deref(),
+		})
+		.await
+}
+
+/// Splits sql into statements at `;` which are not inside a string, a
+/// quoted identifier, a dollar quoted string or a comment
+fn split_statements(sql: &str) -> Vec<&str> {
+	let bytes = sql.as_bytes();
+	let mut statements = vec![];
+	let mut start = 0;
+	let mut i = 0;
+
+	// returns the position after the end or the length
+	let find = |from: usize, end: &[u8]| {
+		bytes[from..]
+			.windows(end.len())
+			.position(|w| w == end)
+			.map(|p| from + p + end.len())
+			.unwrap_or(bytes.len())
+	};
+
+	while i < bytes.len() {
+		i = match bytes[i] {
+			b'\'' => {
+				// E'' strings allow backslash escapes
+				let escapes = i > 0 && matches!(bytes[i - 1], b'E' | b'e');
+				let mut j = i + 1;
+				while j < bytes.len() && bytes[j] != b'\'' {
+					j += if escapes && bytes[j] == b'\\' { 2 } else { 1 };
+				}
+				j + 1
+			}
+			b'"' => find(i + 1, b"\""),
+			b'-' if bytes.get(i + 1) == Some(&b'-') => find(i + 2, b"\n"),
+			b'/' if bytes.get(i + 1) == Some(&b'*') => find(i + 2, b"*/"),
+			b'$' => match dollar_tag(&sql[i..]) {
+				Some(tag) => find(i + tag.len(), tag.as_bytes()),
+				None => i + 1,
+			},
+			b';' => {
+				statements.push(&sql[start..i]);
+				start = i + 1;
+				i + 1
+			}
+			_ => i + 1,
+		};
+	}
+	statements.push(&sql[start.min(sql.len())..]);
+
+	statements
+		.into_iter()
+		.map(str::trim)
+		.filter(|s| !s.is_empty())
+		.collect()
+}
+
+/// Returns the tag like `$body$` if the sql starts with one
+fn dollar_tag(sql: &str) -> Option<&str> {
+	let end = sql[1..].find('$')? + 2;
+	let tag = &sql[1..end - 1];
+
+	let valid = !tag.starts_with(|c: char| c.is_ascii_digit())
+		&& tag.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+	valid.then(|| &sql[..end])
+}
+
+/// Removes the record of the migration, the same way as
+/// [`Migrations::run_all`] finds it
+fn take_record(
+	records: &mut Vec<MigrationRecord>,
+	migration: &Migration,
+) -> Option<MigrationRecord> {
+	let pos = records
+		.iter()
+		.position(|r| r.version == Some(migration.version))
+		.or_else(|| {
+			records.iter().position(|r| {
+				r.version.is_none() && r.name == migration.name.as_ref()
+			})
+		})?;
+
+	Some(records.remove(pos))
+}
+
+/// Creates the migrations table and it's indexes if they don't exist
+///
+/// The indexes are named like postgres names them, so tables created before
+/// don't get a second index.
+fn create_table_sql(table: Ident<'_>, name: &str) -> String {
+	format!(
+		"CREATE TABLE IF NOT EXISTS {table} (\
+			name text NOT NULL, \
+			datetime timestamp, \
+			version bigint UNIQUE, \
+			checksum text, \
+			duration_ms bigint, \
+			down text\
+		); \
+		CREATE UNIQUE INDEX IF NOT EXISTS {} ON {table} (name) \
+		WHERE version IS NULL; \
+		CREATE INDEX IF NOT EXISTS {} ON {table} (datetime);",
+		Ident::new(&format!("{name}_name_idx")),
+		Ident::new(&format!("{name}_datetime_idx")),
+	)
 }
 
 const TABLE_EXISTS: &str = "\
 SELECT EXISTS (
 	SELECT FROM information_schema.tables
-	WHERE table_schema = 'public'
-	AND table_name = 'migrations'
+	WHERE table_schema = coalesce($1::text, current_schema())
+	AND table_name = $2::text
 );";
 
-const CREATE_TABLE: &str = "\
-CREATE TABLE migrations (
-    name text PRIMARY KEY,
-    datetime timestamp
-);
+/// Returns the primary key on the name of tables created before versions
+/// existed
+const NAME_PRIMARY_KEY: &str = "\
+SELECT constraint_name::text
+FROM information_schema.table_constraints
+WHERE table_schema = coalesce($1::text, current_schema())
+AND table_name = $2::text
+AND constraint_type = 'PRIMARY KEY';";
+
+#[cfg(test)]
+mod tests {
+	use super::*;
 
-CREATE INDEX ON migrations (datetime);";
+	fn record(name: &str, version: Option<i64>) -> MigrationRecord {
+		MigrationRecord {
+			name: name.into(),
+			version,
+			datetime: DateTime::now(),
+			checksum: None,
+			duration_ms: None,
+		}
+	}
+
+	#[test]
+	fn split() {
+		let sql = "CREATE INDEX CONCURRENTLY a ON t (x);\n\
+			-- a comment; with a semicolon\n\
+			INSERT INTO t VALUES ('a;b', E'c\\';d', \"e;f\");\n\
+			/* block; comment */\n\
+			CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $body$ \
+			LANGUAGE sql;";
+
+		let statements = split_statements(sql);
+		assert_eq!(statements.len(), 3);
+		assert_eq!(statements[0], "CREATE INDEX CONCURRENTLY a ON t (x)");
+		assert!(statements[1].ends_with("\"e;f\")"));
+		assert!(statements[2].ends_with("$body$ LANGUAGE sql"));
+	}
+
+	#[test]
+	fn take_records() {
+		let set = MigrationSet::new()
+			.add(1, "legacy", "SELECT 1")
+			.add(2, "renamed", "SELECT 2")
+			.add(3, "pending", "SELECT 3");
+		let mut records = vec![
+			record("legacy", None),
+			record("old name", Some(2)),
+			record("unknown", Some(4)),
+		];
+
+		let found: Vec<_> = set
+			.iter()
+			.map(|m| take_record(&mut records, m).map(|r| r.name))
+			.collect();
+		assert_eq!(
+			found,
+			[Some("legacy".into()), Some("old name".into()), None]
+		);
+		assert_eq!(records.len(), 1);
+		assert_eq!(records[0].name, "unknown");
+	}
+}
@@ -0,0 +1,48 @@
+//! Offset pagination
+//!
+//! For large tables prefer [`keyset`](crate::keyset) pagination, since
+//! deep offsets get slow.
+
+/// One page of rows with the total count of all rows
+///
+/// See [`Connection::paginate`](crate::Connection::paginate).
+#[derive(Debug, Clone)]
+pub struct Page<R> {
+	pub items: Vec<R>,
+	/// the count of all rows which match the filter
+	pub total: u64,
+	/// the page number starting at 0
+	pub page: u64,
+	pub per_page: u64,
+}
+
+impl<R> Page<R> {
+	/// Returns the number of pages
+	pub fn pages(&self) -> u64 {
+		match self.per_page {
+			0 => 0,
+			p => self.total.div_ceil(p),
+		}
+	}
+
+	pub fn has_next(&self) -> bool {
+		self.page + 1 < self.pages()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn pages() {
+		let page = Page::<()> {
+			items: vec![],
+			total: 21,
+			page: 1,
+			per_page: 10,
+		};
+		assert_eq!(page.pages(), 3);
+		assert!(page.has_next());
+	}
+}
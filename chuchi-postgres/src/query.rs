@@ -0,0 +1,534 @@
+//! Select queries over multiple tables
+//!
+//! ## Example
+//! ```ignore
+//! use chuchi_postgres::query::Query;
+//! use chuchi_postgres::{filter, Connection, FromRow, TableTempl, ToRow};
+//! use chuchi_postgres::UniqueId;
+//!
+//! #[derive(Debug, FromRow, ToRow, TableTempl)]
+//! struct User {
+//! 	#[index(primary)]
+//! 	id: UniqueId,
+//! 	name: String,
+//! }
+//!
+//! #[derive(Debug, FromRow, ToRow, TableTempl)]
+//! struct Post {
+//! 	#[index(primary)]
+//! 	id: UniqueId,
+//! 	user_id: UniqueId,
+//! 	title: String,
+//! }
+//!
+//! async fn users_with_posts(
+//! 	conn: Connection<'_>,
+//! 	name: &str,
+//! ) -> chuchi_postgres::Result<Vec<(User, Option<Post>)>> {
+//! 	Query::new("users", "u")
+//! 		.left_join("posts", "p")
+//! 		.on(("p", "user_id"), ("u", "id"))
+//! 		.select(conn, filter!("u"."name" = &name ORDER "p"."title" ASC))
+//! 		.await
+//! }
+//! ```
+
+use std::borrow::{Borrow, Cow};
+use std::error::Error as StdError;
+use std::fmt;
+use std::fmt::Write;
+
+use futures_util::{StreamExt, TryStreamExt};
+
+use postgres_types::ToSql;
+
+use crate::filter::{
+	ColumnRef, Filter, FilterFormatter, Limit, Params, Select, SqlStr, SubQuery,
+};
+use crate::row::FromRow;
+use crate::table::TableTemplate;
+use crate::{Connection, Error, Ident, Row};
+
+/// A select query with joins
+///
+/// The columns of each table are selected as `"alias.column"` so the same
+/// column name in multiple tables does not collide.
+/// Rows are returned as a tuple with one element per table, map the tuple to
+/// get a combined struct.
+#[derive(Debug)]
+pub struct Query<'a> {
+	with: Vec<(SqlStr, SubQuery)>,
+	table: Cow<'static, str>,
+	alias: Cow<'static, str>,
+	joins: Vec<Join>,
+	/// params of the `WITH` clauses
+	params: Params<'a>,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Join {
+	pub kind: JoinKind,
+	pub table: Cow<'static, str>,
+	pub alias: Cow<'static, str>,
+	/// all conditions get combined with AND
+	pub on: Vec<(ColumnRef, ColumnRef)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+	Inner,
+	Left,
+}
+
+impl JoinKind {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Self::Inner => "INNER JOIN",
+			Self::Left => "LEFT JOIN",
+		}
+	}
+}
+
+impl<'a> Query<'a> {
+	/// Creates a query which selects from the given table
+	pub fn new(
+		table: impl Into<Cow<'static, str>>,
+		alias: impl Into<Cow<'static, str>>,
+	) -> Self {
+		Self {
+			with: vec![],
+			table: table.into(),
+			alias: alias.into(),
+			joins: vec![],
+			params: Params::new(),
+		}
+	}
+
+	/// Adds `WITH "name" AS (SELECT ...)` which can be used like a table
+	pub fn with(mut self, name: impl Into<SqlStr>, select: Select<'a>) -> Self {
+		self.with.push((name.into(), select.query));
+		self.params.append(select.params);
+		self
+	}
+
+	pub fn inner_join(
+		self,
+		table: impl Into<Cow<'static, str>>,
+		alias: impl Into<Cow<'static, str>>,
+	) -> Self {
+		self.join(JoinKind::Inner, table, alias)
+	}
+
+	pub fn left_join(
+		self,
+		table: impl Into<Cow<'static, str>>,
+		alias: impl Into<Cow<'static, str>>,
+	) -> Self {
+		self.join(JoinKind::Left, table, alias)
+	}
+
+	pub fn join(
+		mut self,
+		kind: JoinKind,
+		table: impl Into<Cow<'static, str>>,
+		alias: impl Into<Cow<'static, str>>,
+	) -> Self {
+		self.joins.push(Join {
+			kind,
+			table: table.into(),
+			alias: alias.into(),
+			on: vec![],
+		});
+		self
+	}
+
+	/// Adds the condition `left = right` to the last join
+	///
+	/// ## Panics
+	/// If no join was added.
+	pub fn on(
+		mut self,
+		left: impl Into<ColumnRef>,
+		right: impl Into<ColumnRef>,
+	) -> Self {
+		self.joins
+			.last_mut()
+			.expect("on can only be called after a join")
+			.on
+			.push((left.into(), right.into()));
+		self
+	}
+
+	/// Returns the aliases of all tables in the order they were added
+	pub fn aliases(&self) -> impl Iterator<Item = &str> {
+		std::iter::once(self.alias.as_ref())
+			.chain(self.joins.iter().map(|j| j.alias.as_ref()))
+	}
+
+	fn to_sql(&self, columns: &str, mut filter: FilterFormatter) -> String {
+		filter.param_start = self.params.len();
+
+		format!(
+			"{}SELECT {columns}{self}{filter}",
+			WithFormatter(&self.with)
+		)
+	}
+
+	fn join_columns<R>(&self) -> Result<String, Error>
+	where
+		R: FromJoinRow,
+	{
+		let aliases: Vec<_> = self.aliases().collect();
+		let mut columns = String::new();
+		R::select_columns(&aliases, &mut columns)?;
+
+		Ok(columns)
+	}
+
+	/// the params of the `WITH` clauses followed by the params of the filter
+	fn params<'b>(
+		&'b self,
+		filter: &'b Filter<'_>,
+	) -> Vec<&'b (dyn ToSql + Sync)> {
+		self.params
+			.iter_to_sql()
+			.chain(filter.params.iter_to_sql())
+			.collect()
+	}
+
+	/// Selects all rows and maps every table to one element of the tuple
+	///
+	/// Returns [`Error::JoinTables`] if the tuple has more elements than the
+	/// query has tables.
+	pub async fn select<R>(
+		&self,
+		conn: Connection<'_>,
+		filter: impl Borrow<Filter<'_>>,
+	) -> Result<Vec<R>, Error>
+	where
+		R: FromJoinRow,
+	{
+		let filter = filter.borrow();
+		let sql =
+			self.to_sql(&self.join_columns::<R>()?, filter.to_formatter());
+		let stmt = conn.prepare_cached(&sql).await?;
+
+		let aliases: Vec<_> = self.aliases().collect();
+
+		conn.query_raw(&stmt, self.params(filter))
+			.await?
+			.map(|row| {
+				row.and_then(|mut row| {
+					R::from_join_row(&mut row, &aliases)
+						.map_err(Error::Deserialize)
+				})
+			})
+			.try_collect()
+			.await
+	}
+
+	/// Like [`Query::select`] but only returns the first row
+	pub async fn select_opt<R>(
+		&self,
+		conn: Connection<'_>,
+		filter: impl Borrow<Filter<'_>>,
+	) -> Result<Option<R>, Error>
+	where
+		R: FromJoinRow,
+	{
+		let filter = filter.borrow();
+		let mut formatter = filter.to_formatter();
+
+		if matches!(formatter.limit, Limit::All) {
+			formatter.limit = &Limit::Fixed(1);
+		}
+
+		let sql = self.to_sql(&self.join_columns::<R>()?, formatter);
+		let stmt = conn.prepare_cached(&sql).await?;
+
+		let aliases: Vec<_> = self.aliases().collect();
+
+		let row: Option<Row> =
+			conn.query_raw_opt(&stmt, self.params(filter)).await?;
+
+		match row {
+			Some(mut row) => R::from_join_row(&mut row, &aliases)
+				.map(Some)
+				.map_err(Error::Deserialize),
+			None => Ok(None),
+		}
+	}
+}
+
+/// Writes `WITH "name" AS (SELECT ...), ... ` if there are any
+struct WithFormatter<'a>(&'a [(SqlStr, SubQuery)]);
+
+impl fmt::Display for WithFormatter<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.0.is_empty() {
+			return Ok(());
+		}
+
+		f.write_str("WITH ")?;
+
+		let mut param_num = 0;
+		for (i, (name, query)) in self.0.iter().enumerate() {
+			if i != 0 {
+				f.write_str(", ")?;
+			}
+
+			let mut query = query.to_formatter();
+			query.param_start = param_num;
+			write!(f, "{} AS (", Ident::new(name))?;
+			param_num = query.fmt_select(f)?;
+			f.write_str(")")?;
+		}
+
+		f.write_str(" ")
+	}
+}
+
+/// Writes the `FROM` and `JOIN` part of the query
+impl fmt::Display for Query<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			" FROM {} AS {}",
+			Ident::table(&self.table),
+			Ident::new(&self.alias)
+		)?;
+
+		for join in &self.joins {
+			write!(
+				f,
+				" {} {} AS {}",
+				join.kind.as_str(),
+				Ident::table(&join.table),
+				Ident::new(&join.alias)
+			)?;
+
+			for (i, (left, right)) in join.on.iter().enumerate() {
+				f.write_str(if i == 0 { " ON " } else { " AND " })?;
+				write!(f, "{} = {}", left, right)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// A row which contains multiple tables
+pub trait FromJoinRow: Sized {
+	/// Writes the selected columns of all tables, the table at index `i`
+	/// uses the alias `aliases[i]`.
+	///
+	/// Returns [`Error::JoinTables`] if there are less aliases than tables.
+	fn select_columns(aliases: &[&str], s: &mut String) -> Result<(), Error>;
+
+	fn from_join_row(
+		row: &mut Row,
+		aliases: &[&str],
+	) -> Result<Self, Box<dyn StdError + Send + Sync>>;
+}
+
+/// One table of a [`FromJoinRow`]
+///
+/// `Option<T>` returns `None` if all columns are null, which happens with a
+/// `LEFT JOIN` without a match.
+pub trait JoinTable: Sized {
+	/// Writes `"alias"."column" AS "alias.column"` for each column
+	fn select_columns(alias: &str, s: &mut String);
+
+	fn from_join_row(
+		row: &mut Row,
+		alias: &str,
+	) -> Result<Self, Box<dyn StdError + Send + Sync>>;
+}
+
+fn table_columns<T>() -> Vec<Cow<'static, str>>
+where
+	T: TableTemplate,
+{
+	T::table_info()
+		.data()
+		.iter()
+		.filter(|c| c.generated.is_none())
+		.map(|c| c.name.clone())
+		.collect()
+}
+
+impl<T> JoinTable for T
+where
+	T: TableTemplate + for<'r> FromRow<'r>,
+{
+	fn select_columns(alias: &str, s: &mut String) {
+		for name in table_columns::<T>() {
+			if !s.is_empty() {
+				s.push_str(", ");
+			}
+
+			let as_name = format!("{alias}.{name}");
+			write!(
+				s,
+				"{}.{} AS {}",
+				Ident::new(alias),
+				Ident::new(&name),
+				Ident::new(&as_name)
+			)
+			.unwrap();
+		}
+	}
+
+	fn from_join_row(
+		row: &mut Row,
+		alias: &str,
+	) -> Result<Self, Box<dyn StdError + Send + Sync>> {
+		let prev = row.prefix().map(ToString::to_string);
+		row.set_prefix(Some(format!("{alias}.")));
+		let r = T::from_row(row);
+		row.set_prefix(prev);
+
+		r
+	}
+}
+
+impl<T> JoinTable for Option<T>
+where
+	T: TableTemplate + for<'r> FromRow<'r>,
+{
+	fn select_columns(alias: &str, s: &mut String) {
+		<T as JoinTable>::select_columns(alias, s);
+	}
+
+	fn from_join_row(
+		row: &mut Row,
+		alias: &str,
+	) -> Result<Self, Box<dyn StdError + Send + Sync>> {
+		for name in table_columns::<T>() {
+			if !row.is_null(format!("{alias}.{name}").as_str())? {
+				return T::from_join_row(row, alias).map(Some);
+			}
+		}
+
+		Ok(None)
+	}
+}
+
+fn check_aliases(aliases: &[&str], tables: usize) -> Result<(), Error> {
+	match aliases.len() < tables {
+		true => Err(Error::JoinTables {
+			tables,
+			aliases: aliases.len(),
+		}),
+		false => Ok(()),
+	}
+}
+
+macro_rules! impl_tuple {
+	($tables:literal; $($name:ident => $idx:tt),*) => {
+		impl<$($name),*> FromJoinRow for ($($name),*)
+		where
+			$($name: JoinTable),*
+		{
+			fn select_columns(
+				aliases: &[&str],
+				s: &mut String,
+			) -> Result<(), Error> {
+				check_aliases(aliases, $tables)?;
+				$(
+					<$name as JoinTable>::select_columns(aliases[$idx], s);
+				)*
+
+				Ok(())
+			}
+
+			fn from_join_row(
+				row: &mut Row,
+				aliases: &[&str],
+			) -> Result<Self, Box<dyn StdError + Send + Sync>> {
+				check_aliases(aliases, $tables)?;
+				Ok(($(
+					<$name as JoinTable>::from_join_row(row, aliases[$idx])?
+				),*))
+			}
+		}
+	};
+}
+
+impl_tuple!(2; A => 0, B => 1);
+impl_tuple!(3; A => 0, B => 1, C => 2);
+impl_tuple!(4; A => 0, B => 1, C => 2, D => 3);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::{filter, FromRow, TableTempl, ToRow, UniqueId};
+
+	#[derive(Debug, FromRow, ToRow, TableTempl)]
+	struct User {
+		#[index(primary)]
+		id: UniqueId,
+		name: String,
+	}
+
+	#[derive(Debug, FromRow, ToRow, TableTempl)]
+	struct Post {
+		#[index(primary)]
+		id: UniqueId,
+		user_id: UniqueId,
+	}
+
+	#[test]
+	fn left_join() {
+		let query = Query::new("users", "u")
+			.left_join("posts", "p")
+			.on(("p", "user_id"), ("u", "id"));
+		let name = "Alice";
+
+		let filter = filter!("u"."name" = &name);
+		let sql = query.to_sql(
+			&query.join_columns::<(User, Option<Post>)>().unwrap(),
+			filter.to_formatter(),
+		);
+		assert_eq!(
+			sql,
+			"SELECT \"u\".\"id\" AS \"u.id\", \"u\".\"name\" AS \"u.name\", \
+			 \"p\".\"id\" AS \"p.id\", \"p\".\"user_id\" AS \"p.user_id\" \
+			 FROM \"users\" AS \"u\" LEFT JOIN \"posts\" AS \"p\" \
+			 ON \"p\".\"user_id\" = \"u\".\"id\" WHERE \"u\".\"name\" = $1"
+		);
+	}
+
+	#[test]
+	fn with() {
+		let min = 3;
+		let name = "Alice";
+		let query = Query::new("popular", "p")
+			.with("popular", Select::new("posts", filter!("likes" > &min)));
+
+		let filter = filter!("p"."title" = &name);
+		let sql = query.to_sql("*", filter.to_formatter());
+		assert_eq!(
+			sql,
+			"WITH \"popular\" AS (SELECT * FROM \"posts\" \
+			 WHERE \"likes\" > $1) SELECT * FROM \"popular\" AS \"p\" \
+			 WHERE \"p\".\"title\" = $2"
+		);
+		assert_eq!(query.params(&filter).len(), 2);
+	}
+
+	#[test]
+	fn missing_join() {
+		let query = Query::new("users", "u");
+		let res = query.join_columns::<(User, Option<Post>)>();
+		assert!(matches!(
+			res,
+			Err(Error::JoinTables {
+				tables: 2,
+				aliases: 1
+			})
+		));
+	}
+}
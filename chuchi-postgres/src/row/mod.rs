@@ -10,11 +10,12 @@ use std::{
 
 use futures_util::Stream;
 use pin_project_lite::pin_project;
-use postgres_types::{FromSql, ToSql};
+use postgres_types::{FromSql, ToSql, Type};
 use tokio_postgres::row::RowIndex;
 pub use tokio_postgres::Column;
 
 use crate::connection::Error;
+use crate::Ident;
 
 pub use from::{FromRow, FromRowOwned};
 pub use to::{ToRow, ToRowStatic};
@@ -25,9 +26,9 @@ pub trait NamedColumns {
 }
 
 #[derive(Debug)]
-#[repr(transparent)]
 pub struct Row {
 	row: tokio_postgres::Row,
+	prefix: Option<String>,
 }
 
 impl Row {
@@ -46,6 +47,31 @@ impl Row {
 		self.row.len()
 	}
 
+	/// Set's a prefix which get's prepended to the name when accessing a
+	/// column.
+	///
+	/// This is used when multiple tables are selected which have the same
+	/// column names.
+	///
+	/// ## Note
+	/// While a prefix is set, columns can only be accessed by their name.
+	pub fn set_prefix(&mut self, prefix: Option<String>) {
+		self.prefix = prefix;
+	}
+
+	/// Returns the prefix which is currently set.
+	pub fn prefix(&self) -> Option<&str> {
+		self.prefix.as_deref()
+	}
+
+	/// Returns true if the column is null, ignoring it's type.
+	pub fn is_null<I>(&self, idx: I) -> Result<bool, tokio_postgres::Error>
+	where
+		I: RowIndex + std::fmt::Display,
+	{
+		self.try_get::<_, AnyNull>(idx).map(|n| n.0)
+	}
+
 	/// Deserializes the row.
 	pub fn deserialize<'a, T>(
 		&'a self,
@@ -80,7 +106,10 @@ impl Row {
 		I: RowIndex + std::fmt::Display,
 		T: FromSql<'a>,
 	{
-		self.row.get(idx)
+		match &self.prefix {
+			Some(prefix) => self.row.get(format!("{prefix}{idx}").as_str()),
+			None => self.row.get(idx),
+		}
 	}
 
 	/// Like [`Row::get()`], but returns a [`Result`] rather than panicking.
@@ -92,13 +121,38 @@ impl Row {
 		I: RowIndex + std::fmt::Display,
 		T: FromSql<'a>,
 	{
-		self.row.try_get(idx)
+		match &self.prefix {
+			Some(prefix) => self.row.try_get(format!("{prefix}{idx}").as_str()),
+			None => self.row.try_get(idx),
+		}
 	}
 }
 
 impl From<tokio_postgres::Row> for Row {
 	fn from(row: tokio_postgres::Row) -> Self {
-		Self { row }
+		Self { row, prefix: None }
+	}
+}
+
+/// Accepts every type and only checks if the value is null
+struct AnyNull(bool);
+
+impl<'a> FromSql<'a> for AnyNull {
+	fn from_sql(
+		_ty: &Type,
+		_raw: &'a [u8],
+	) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+		Ok(Self(false))
+	}
+
+	fn from_sql_null(
+		_ty: &Type,
+	) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+		Ok(Self(true))
+	}
+
+	fn accepts(_ty: &Type) -> bool {
+		true
 	}
 }
 
@@ -153,9 +207,7 @@ impl<'a> RowBuilder<'a> {
 
 	/// Push a new column to the row.
 	///
-	/// ## Note
-	/// Do not use untrusted names this might lead to
-	/// SQL injection.
+	/// The name get's quoted with [`Ident`].
 	pub fn push(
 		&mut self,
 		name: &'a str,
@@ -174,7 +226,7 @@ impl ToRow for RowBuilder<'_> {
 				s.push_str(", ");
 			}
 
-			write!(s, "\"{k}\"").unwrap();
+			write!(s, "{}", Ident::new(k)).unwrap();
 		}
 	}
 
@@ -194,7 +246,7 @@ impl ToRow for RowBuilder<'_> {
 				s.push_str(", ");
 			}
 
-			write!(s, "\"{k}\" = ${}", i + 1).unwrap();
+			write!(s, "{} = ${}", Ident::new(k), i + 1).unwrap();
 		}
 	}
 
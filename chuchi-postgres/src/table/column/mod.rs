@@ -1,16 +1,22 @@
 mod column_type;
 pub use column_type::ColumnType;
 
+use std::borrow::Cow;
+
+use crate::Ident;
+
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct Column {
-	pub name: &'static str,
+	pub name: Cow<'static, str>,
 	pub kind: ColumnKind,
 	pub index: IndexKind,
+	pub generated: Option<Generated>,
 }
 
 impl Column {
 	pub fn new<T>(
-		name: &'static str,
+		name: impl Into<Cow<'static, str>>,
 		len: Option<usize>,
 		index: IndexKind,
 	) -> Self
@@ -28,7 +34,76 @@ impl Column {
 			};
 		}
 
-		Self { name, kind, index }
+		Self::with_kind(name, kind, index)
+	}
+
+	/// Creates a column with the given kind, for kinds which no
+	/// [`ColumnType`] returns
+	pub fn with_kind(
+		name: impl Into<Cow<'static, str>>,
+		kind: ColumnKind,
+		index: IndexKind,
+	) -> Self {
+		Self {
+			name: name.into(),
+			kind,
+			index,
+			generated: None,
+		}
+	}
+
+	/// Creates a generated tsvector column over the given text columns
+	/// which gets a GIN index.
+	pub fn ts_vector<I>(
+		name: impl Into<Cow<'static, str>>,
+		config: impl Into<Cow<'static, str>>,
+		columns: I,
+	) -> Self
+	where
+		I: IntoIterator,
+		I::Item: Into<Cow<'static, str>>,
+	{
+		Self {
+			name: name.into(),
+			kind: ColumnKind::TsVector,
+			index: IndexKind::Index,
+			generated: Some(Generated::TsVector {
+				config: config.into(),
+				columns: columns.into_iter().map(Into::into).collect(),
+			}),
+		}
+	}
+}
+
+/// A column which is computed from other columns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Generated {
+	/// `to_tsvector(config, "a" || ' ' || "b")`
+	TsVector {
+		/// the text search configuration for example `english`
+		config: Cow<'static, str>,
+		columns: Vec<Cow<'static, str>>,
+	},
+}
+
+impl Generated {
+	/// Returns the sql expression which generates the column
+	pub fn to_sql(&self) -> String {
+		match self {
+			Self::TsVector { config, columns } => {
+				let text = columns
+					.iter()
+					.map(|c| format!("coalesce({}, '')", Ident::new(c)))
+					.collect::<Vec<_>>()
+					.join(" || ' ' || ");
+
+				format!(
+					"to_tsvector('{}', {})",
+					config.replace('\'', "''"),
+					if text.is_empty() { "''" } else { &text }
+				)
+			}
+		}
 	}
 }
 
@@ -54,6 +129,7 @@ pub enum ColumnKind {
 	TextArray,
 	Bytea,
 	Json,
+	TsVector,
 }
 
 impl ColumnKind {
@@ -75,6 +151,7 @@ impl ColumnKind {
 			Self::TextArray => "text []",
 			Self::Bytea => "bytea",
 			Self::Json => "json",
+			Self::TsVector => "tsvector",
 		}
 	}
 
@@ -82,7 +159,9 @@ impl ColumnKind {
 		match self {
 			// Self::Char(v) => Some(v.to_string()),
 			Self::Varchar(v) => format!("({})", v),
-			Self::FixedText(v) => format!(" CHECK (length({})={})", name, v),
+			Self::FixedText(v) => {
+				format!(" CHECK (length({})={})", Ident::new(name), v)
+			}
 			Self::Option(t) => t.value(name),
 			_ => String::new(),
 		}
@@ -92,6 +171,15 @@ impl ColumnKind {
 		format!("{}{}", self.short(), self.value(name))
 	}
 
+	/// Returns the index method which should be used for this kind
+	pub fn index_method(&self) -> Option<&'static str> {
+		match self {
+			Self::TsVector => Some("GIN"),
+			Self::Option(t) => t.index_method(),
+			_ => None,
+		}
+	}
+
 	pub fn not_null_str(&self) -> &'static str {
 		match self {
 			Self::Option(_) => "null",
@@ -104,7 +192,7 @@ impl ColumnKind {
 pub enum IndexKind {
 	Primary,
 	Unique,
-	NamedUnique(&'static str),
+	NamedUnique(Cow<'static, str>),
 	Index,
 	None,
 }
@@ -189,3 +277,20 @@ txid_snapshot	 	user-level transaction ID snapshot
 uuid	 	universally unique identifier
 xml	 	XML data
 */
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fixed_text_quotes_name() {
+		assert_eq!(
+			ColumnKind::FixedText(6).to_string("Code"),
+			r#"text CHECK (length("Code")=6)"#
+		);
+		assert_eq!(
+			ColumnKind::FixedText(2).value("a\")=1 OR (\"b"),
+			r#" CHECK (length("a"")=1 OR (""b")=2)"#
+		);
+	}
+}
@@ -1,6 +1,6 @@
 use super::column::Column;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Info {
 	data: Vec<Column>,
 }
@@ -24,10 +24,8 @@ impl Info {
 		&self.data
 	}
 
-	pub fn names<'a>(
-		&'a self,
-	) -> impl ExactSizeIterator<Item = &'static str> + 'a {
-		self.data.iter().map(|v| v.name)
+	pub fn names(&self) -> impl ExactSizeIterator<Item = &str> {
+		self.data.iter().map(|v| v.name.as_ref())
 	}
 }
 
@@ -0,0 +1,299 @@
+//! Reads the [`Info`] of a table from the database
+
+use std::collections::HashMap;
+
+use super::column::{Column, ColumnKind, Generated, IndexKind};
+use super::Info;
+use crate::connection::Connection;
+use crate::{Error, Ident};
+
+use chuchi_postgres_derive::FromRow;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TableInfoError {
+	#[error("Table {0} does not exist")]
+	NotFound(String),
+
+	#[error("Column {column} has the type {ty} which has no ColumnKind")]
+	UnknownType { column: String, ty: String },
+
+	#[error(transparent)]
+	Postgres(#[from] Error),
+}
+
+#[derive(Debug, FromRow)]
+struct Relation {
+	schema: String,
+	name: String,
+}
+
+#[derive(Debug, FromRow)]
+struct ColumnRow {
+	name: String,
+	udt: String,
+	nullable: bool,
+	max_len: Option<i32>,
+	generated: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct IndexRow {
+	column_name: String,
+	index_name: String,
+	is_primary: bool,
+	is_unique: bool,
+	column_count: i16,
+}
+
+#[derive(Debug, FromRow)]
+struct CheckRow {
+	column_name: String,
+	def: String,
+}
+
+pub(crate) async fn table_info(
+	conn: &Connection<'_>,
+	table: Ident<'_>,
+) -> Result<Info, TableInfoError> {
+	let regclass = table.to_string();
+
+	let relation: Option<Relation> = conn
+		.query_opt(
+			"SELECT n.nspname::text AS schema, c.relname::text AS name \
+			FROM pg_class c \
+			JOIN pg_namespace n ON n.oid = c.relnamespace \
+			WHERE c.oid = to_regclass($1)",
+			&[&regclass],
+		)
+		.await?;
+	let Some(relation) = relation else {
+		return Err(TableInfoError::NotFound(table.to_string()));
+	};
+
+	let columns: Vec<ColumnRow> = conn
+		.query(
+			"SELECT column_name::text AS name, udt_name::text AS udt, \
+			is_nullable = 'YES' AS nullable, \
+			character_maximum_length::int4 AS max_len, \
+			generation_expression::text AS generated \
+			FROM information_schema.columns \
+			WHERE table_schema = $1 AND table_name = $2 \
+			ORDER BY ordinal_position",
+			&[&relation.schema, &relation.name],
+		)
+		.await?;
+
+	// partial and expression indexes can't be represented by an IndexKind
+	let index_rows: Vec<IndexRow> = conn
+		.query(
+			"SELECT a.attname::text AS column_name, \
+			ic.relname::text AS index_name, i.indisprimary AS is_primary, \
+			i.indisunique AS is_unique, i.indnatts AS column_count \
+			FROM pg_index i \
+			JOIN pg_class ic ON ic.oid = i.indexrelid \
+			JOIN pg_attribute a \
+			ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey) \
+			WHERE i.indrelid = to_regclass($1) AND i.indpred IS NULL \
+			AND i.indexprs IS NULL",
+			&[&regclass],
+		)
+		.await?;
+
+	let checks: Vec<CheckRow> = conn
+		.query(
+			"SELECT a.attname::text AS column_name, \
+			pg_get_constraintdef(c.oid) AS def \
+			FROM pg_constraint c \
+			JOIN pg_attribute a \
+			ON a.attrelid = c.conrelid AND a.attnum = ANY(c.conkey) \
+			WHERE c.conrelid = to_regclass($1) AND c.contype = 'c' \
+			AND array_length(c.conkey, 1) = 1",
+			&[&regclass],
+		)
+		.await?;
+
+	let mut indexes: HashMap<String, IndexKind> = HashMap::new();
+	for row in index_rows {
+		let kind = match (row.is_primary, row.is_unique, row.column_count) {
+			(true, _, _) => IndexKind::Primary,
+			(_, true, count) => {
+				match named_unique(&row.index_name, &relation.name) {
+					Some(name) => {
+						IndexKind::NamedUnique(name.to_string().into())
+					}
+					None if count == 1 => IndexKind::Unique,
+					None => IndexKind::NamedUnique(row.index_name.into()),
+				}
+			}
+			(_, _, 1) => IndexKind::Index,
+			_ => continue,
+		};
+
+		let prev = indexes.entry(row.column_name).or_insert(IndexKind::None);
+		if index_rank(&kind) > index_rank(prev) {
+			*prev = kind;
+		}
+	}
+
+	let mut info = Info::with_capacity(columns.len());
+	for col in columns {
+		let fixed_len = checks
+			.iter()
+			.filter(|c| c.column_name == col.name)
+			.find_map(|c| parse_fixed_len(&c.def, &col.name));
+
+		let mut kind = match fixed_len {
+			Some(len) if col.udt == "text" => ColumnKind::FixedText(len),
+			_ => column_kind(&col.udt, col.max_len).ok_or_else(|| {
+				TableInfoError::UnknownType {
+					column: col.name.clone(),
+					ty: col.udt.clone(),
+				}
+			})?,
+		};
+		if col.nullable {
+			kind = ColumnKind::Option(Box::new(kind));
+		}
+
+		let generated = col.generated.as_deref().and_then(parse_ts_vector);
+
+		info.push(Column {
+			index: indexes.remove(&col.name).unwrap_or(IndexKind::None),
+			name: col.name.into(),
+			kind,
+			generated,
+		});
+	}
+
+	Ok(info)
+}
+
+fn index_rank(kind: &IndexKind) -> u8 {
+	match kind {
+		IndexKind::Primary => 4,
+		IndexKind::Unique => 3,
+		IndexKind::NamedUnique(_) => 2,
+		IndexKind::Index => 1,
+		IndexKind::None => 0,
+	}
+}
+
+/// Returns the name of the [`IndexKind::NamedUnique`] if the index was created
+/// by a `TableTemplate`, which names it `{table}_{name}_uidx`
+fn named_unique<'a>(index_name: &'a str, table: &str) -> Option<&'a str> {
+	index_name
+		.strip_prefix(table)?
+		.strip_prefix('_')?
+		.strip_suffix("_uidx")
+}
+
+/// Maps the `udt_name` of `information_schema.columns` to a ColumnKind
+///
+/// Types which would only match a ColumnKind approximately, like
+/// `timestamptz` or `jsonb`, return None so differences don't get hidden.
+fn column_kind(udt: &str, max_len: Option<i32>) -> Option<ColumnKind> {
+	let kind = match udt {
+		"bool" => ColumnKind::Boolean,
+		// an unbounded varchar is not the same as text
+		"varchar" => ColumnKind::Varchar(max_len? as usize),
+		"text" => ColumnKind::Text,
+		"date" => ColumnKind::Date,
+		"timestamp" => ColumnKind::Timestamp,
+		"float8" => ColumnKind::F64,
+		"float4" => ColumnKind::F32,
+		"int8" => ColumnKind::I64,
+		"int4" => ColumnKind::I32,
+		"int2" => ColumnKind::I16,
+		"_text" => ColumnKind::TextArray,
+		"bytea" => ColumnKind::Bytea,
+		"json" => ColumnKind::Json,
+		"tsvector" => ColumnKind::TsVector,
+		_ => return None,
+	};
+
+	Some(kind)
+}
+
+/// Parses `CHECK ((length(code) = 6))` which is created by
+/// `ColumnKind::FixedText`
+fn parse_fixed_len(def: &str, column: &str) -> Option<usize> {
+	let rest = def.strip_prefix("CHECK ((length(")?.strip_suffix("))")?;
+	let (name, len) = rest.split_once(") = ")?;
+
+	if unquote(name) != column {
+		return None;
+	}
+
+	len.parse().ok()
+}
+
+/// Parses the expression created by [`Generated::TsVector`] as postgres
+/// returns it
+///
+/// `to_tsvector('german'::regconfig, ((COALESCE(title, ''::text) || ' '::text)
+/// || COALESCE(body, ''::text)))`
+fn parse_ts_vector(expr: &str) -> Option<Generated> {
+	let rest = expr.strip_prefix("to_tsvector('")?;
+	let (config, rest) = rest.split_once("'::regconfig, ")?;
+
+	let columns = rest
+		.split("COALESCE(")
+		.skip(1)
+		.map(|part| {
+			let (name, _) = part.split_once(", ''::text)")?;
+			Some(unquote(name).into())
+		})
+		.collect::<Option<_>>()?;
+
+	Some(Generated::TsVector {
+		config: config.replace("''", "'").into(),
+		columns,
+	})
+}
+
+fn unquote(name: &str) -> String {
+	match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+		Some(name) => name.replace("\"\"", "\""),
+		None => name.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_constraints() {
+		assert_eq!(
+			parse_fixed_len("CHECK ((length(code) = 6))", "code"),
+			Some(6)
+		);
+		assert_eq!(
+			parse_fixed_len("CHECK ((length(\"Code\") = 12))", "Code"),
+			Some(12)
+		);
+		assert_eq!(
+			parse_fixed_len("CHECK ((length(code) = 6))", "other"),
+			None
+		);
+		assert_eq!(parse_fixed_len("CHECK ((code <> ''::text))", "code"), None);
+
+		let generated = parse_ts_vector(
+			"to_tsvector('german'::regconfig, ((COALESCE(\"Title\", ''::text) \
+			|| ' '::text) || COALESCE(body, ''::text)))",
+		);
+		assert_eq!(
+			generated,
+			Some(Generated::TsVector {
+				config: "german".into(),
+				columns: vec!["Title".into(), "body".into()]
+			})
+		);
+		assert_eq!(parse_ts_vector("lower(name)"), None);
+
+		assert_eq!(named_unique("user_email_uidx", "user"), Some("email"));
+		assert_eq!(named_unique("user_a_b_key", "user"), None);
+		assert_eq!(column_kind("varchar", None), None);
+	}
+}
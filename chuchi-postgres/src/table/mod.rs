@@ -3,9 +3,13 @@ pub use info::Info;
 
 pub mod column;
 
+pub(crate) mod introspect;
+pub use introspect::TableInfoError;
+
 pub mod table_owned;
 pub use table_owned::TableOwned;
 
+#[allow(clippy::module_inception)]
 pub mod table;
 pub use table::Table;
 
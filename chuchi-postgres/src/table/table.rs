@@ -2,26 +2,56 @@ use std::borrow::{Borrow, Cow};
 
 use crate::{
 	filter::{Filter, WhereFilter},
+	paginate::Page,
 	row::{FromRowOwned, NamedColumns, ToRow, ToRowStatic},
-	Connection, Error,
+	Connection, Error, Ident,
 };
 
 #[derive(Debug, Clone)]
 pub struct Table {
+	schema: Option<Cow<'static, str>>,
 	name: Cow<'static, str>,
 }
 
 impl Table {
 	pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
-		Self { name: name.into() }
+		Self {
+			schema: None,
+			name: name.into(),
+		}
+	}
+
+	/// Creates a table which lives in the given schema
+	pub fn with_schema(
+		schema: impl Into<Cow<'static, str>>,
+		name: impl Into<Cow<'static, str>>,
+	) -> Self {
+		Self {
+			schema: Some(schema.into()),
+			name: name.into(),
+		}
 	}
 
 	pub fn name(&self) -> &str {
 		self.name.as_ref()
 	}
 
+	pub fn schema(&self) -> Option<&str> {
+		self.schema.as_deref()
+	}
+
+	/// Returns the name qualified with the schema if there is one
+	///
+	/// Without a schema the name is parsed like [`Ident::table`].
+	pub fn ident(&self) -> Ident<'_> {
+		match &self.schema {
+			Some(schema) => Ident::with_schema(schema, &self.name),
+			None => Ident::table(&self.name),
+		}
+	}
+
 	pub fn with_conn<'a>(&'a self, conn: Connection<'a>) -> TableWithConn<'a> {
-		TableWithConn { table: &self, conn }
+		TableWithConn { table: self, conn }
 	}
 }
 
@@ -37,6 +67,11 @@ impl TableWithConn<'_> {
 		self.table.name.as_ref()
 	}
 
+	/// Get the name including the schema
+	pub fn ident(&self) -> Ident<'_> {
+		self.table.ident()
+	}
+
 	pub async fn select<R>(
 		&self,
 		filter: impl Borrow<Filter<'_>>,
@@ -44,7 +79,7 @@ impl TableWithConn<'_> {
 	where
 		R: FromRowOwned + NamedColumns,
 	{
-		self.conn.select(self.name(), filter).await
+		self.conn.select(self.ident(), filter).await
 	}
 
 	pub async fn select_one<R>(
@@ -54,7 +89,7 @@ impl TableWithConn<'_> {
 	where
 		R: FromRowOwned + NamedColumns,
 	{
-		self.conn.select_one(self.name(), filter).await
+		self.conn.select_one(self.ident(), filter).await
 	}
 
 	pub async fn select_opt<R>(
@@ -64,7 +99,7 @@ impl TableWithConn<'_> {
 	where
 		R: FromRowOwned + NamedColumns,
 	{
-		self.conn.select_opt(self.name(), filter).await
+		self.conn.select_opt(self.ident(), filter).await
 	}
 
 	pub async fn count(
@@ -72,14 +107,28 @@ impl TableWithConn<'_> {
 		column: &str,
 		filter: impl Borrow<Filter<'_>>,
 	) -> Result<u64, Error> {
-		self.conn.count(self.name(), column, filter).await
+		self.conn.count(self.ident(), column, filter).await
+	}
+
+	pub async fn paginate<R>(
+		&self,
+		filter: impl Borrow<Filter<'_>>,
+		page: u64,
+		per_page: u64,
+	) -> Result<Page<R>, Error>
+	where
+		R: FromRowOwned + NamedColumns,
+	{
+		self.conn
+			.paginate(self.ident(), filter, page, per_page)
+			.await
 	}
 
 	pub async fn insert<U>(&self, item: &U) -> Result<(), Error>
 	where
 		U: ToRow,
 	{
-		self.conn.insert(self.name(), item).await
+		self.conn.insert(self.ident(), item).await
 	}
 
 	pub async fn insert_many<U, I>(&self, items: I) -> Result<(), Error>
@@ -88,7 +137,7 @@ impl TableWithConn<'_> {
 		I: IntoIterator,
 		I::Item: Borrow<U>,
 	{
-		self.conn.insert_many(self.name(), items).await
+		self.conn.insert_many(self.ident(), items).await
 	}
 
 	pub async fn update<U>(
@@ -99,14 +148,14 @@ impl TableWithConn<'_> {
 	where
 		U: ToRow,
 	{
-		self.conn.update(self.name(), item, filter).await
+		self.conn.update(self.ident(), item, filter).await
 	}
 
 	pub async fn delete(
 		&self,
 		filter: impl Borrow<WhereFilter<'_>>,
 	) -> Result<(), Error> {
-		self.conn.delete(self.name(), filter).await
+		self.conn.delete(self.ident(), filter).await
 	}
 
 	pub fn conn(&self) -> &Connection<'_> {
@@ -8,8 +8,9 @@ use super::{Info, TableTemplate};
 use crate::connection::ConnectionOwned;
 use crate::database::DatabaseError;
 use crate::filter::{Filter, WhereFilter};
+use crate::paginate::Page;
 use crate::row::ToRow;
-use crate::{filter, Database, Error, Result};
+use crate::{filter, Database, Error, Ident, Result};
 
 use std::borrow::Borrow;
 use std::marker::PhantomData;
@@ -26,6 +27,7 @@ where
 	T: TableTemplate,
 {
 	db: Database,
+	schema: Option<&'static str>,
 	name: &'static str,
 	meta: Arc<TableMeta>,
 	phantom: PhantomData<T>,
@@ -35,12 +37,17 @@ impl<T> TableOwned<T>
 where
 	T: TableTemplate,
 {
-	pub(crate) fn new(db: Database, name: &'static str) -> Self {
+	pub(crate) fn new(
+		db: Database,
+		schema: Option<&'static str>,
+		name: &'static str,
+	) -> Self {
 		let info = T::table_info();
 		let meta = TableMeta { info };
 
 		Self {
 			db,
+			schema,
 			name,
 			meta: Arc::new(meta),
 			phantom: PhantomData,
@@ -51,6 +58,20 @@ where
 		self.name
 	}
 
+	pub fn schema(&self) -> Option<&'static str> {
+		self.schema
+	}
+
+	/// Returns the name qualified with the schema if there is one
+	///
+	/// Without a schema the name is parsed like [`Ident::table`].
+	pub fn ident(&self) -> Ident<'static> {
+		match self.schema {
+			Some(schema) => Ident::with_schema(schema, self.name),
+			None => Ident::table(self.name),
+		}
+	}
+
 	pub fn info(&self) -> &Info {
 		&self.meta.info
 	}
@@ -64,7 +85,14 @@ where
 
 	// Create
 	pub async fn try_create(&self) -> Result<()> {
-		let sql = info_data_to_sql(self.name, self.meta.info.data());
+		let mut sql = info_data_to_sql(self.ident(), self.meta.info.data());
+		if let Some(schema) = self.schema {
+			sql = format!(
+				"CREATE SCHEMA IF NOT EXISTS {}; {}",
+				Ident::new(schema),
+				sql
+			);
+		}
 
 		self.get_connection()
 			.await?
@@ -87,7 +115,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.insert(self.name, input)
+			.insert(self.ident(), input)
 			.await
 	}
 
@@ -100,7 +128,7 @@ where
 		let trans = conn.transaction().await?;
 		let conn = trans.connection();
 
-		conn.insert_many(self.name, input).await?;
+		conn.insert_many(self.ident(), input).await?;
 
 		trans.commit().await?;
 
@@ -114,7 +142,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.select(self.name, filter!())
+			.select(self.ident(), filter!())
 			.await
 	}
 
@@ -125,7 +153,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.select(self.name, filter)
+			.select(self.ident(), filter)
 			.await
 	}
 
@@ -136,7 +164,20 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.select_opt(self.name, filter)
+			.select_opt(self.ident(), filter)
+			.await
+	}
+
+	pub async fn paginate(
+		&self,
+		filter: impl Borrow<Filter<'_>>,
+		page: u64,
+		per_page: u64,
+	) -> Result<Page<T>> {
+		self.get_connection()
+			.await?
+			.connection()
+			.paginate(self.ident(), filter, page, per_page)
 			.await
 	}
 
@@ -148,7 +189,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.count(self.name, column, filter)
+			.count(self.ident(), column, filter)
 			.await
 	}
 
@@ -164,7 +205,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.update(self.name, item, filter)
+			.update(self.ident(), item, filter)
 			.await
 	}
 
@@ -176,7 +217,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.update(self.name, input, filter)
+			.update(self.ident(), input, filter)
 			.await
 	}
 
@@ -188,7 +229,7 @@ where
 		self.get_connection()
 			.await?
 			.connection()
-			.delete(self.name, filter)
+			.delete(self.ident(), filter)
 			.await
 	}
 }
@@ -200,6 +241,7 @@ where
 	fn clone(&self) -> Self {
 		Self {
 			db: self.db.clone(),
+			schema: self.schema,
 			name: self.name,
 			meta: self.meta.clone(),
 			phantom: PhantomData,
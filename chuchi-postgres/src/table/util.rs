@@ -1,55 +1,83 @@
 use super::column::{Column, IndexKind};
+use crate::Ident;
 
-pub fn info_data_to_sql(name: &str, data: &[Column]) -> String {
+pub fn info_data_to_sql(name: Ident<'_>, data: &[Column]) -> String {
 	let mut primary_indexes = vec![];
 	let mut normal_indexes = vec![];
-	let mut unique_indexes = vec![]; // (name, vec![])
+	let mut unique_indexes = vec![];
+	let mut named_unique_indexes: Vec<(&str, Vec<String>)> = vec![];
 
 	let mut cols_sql = vec![];
 
 	for col in data {
-		let kind = col.kind.to_string(col.name);
+		let kind = col.kind.to_string(&col.name);
 		let not_null = col.kind.not_null_str();
-		let quoted_name = quote(col.name);
+		let quoted_name = quote(&col.name);
 
-		cols_sql.push(format!("{} {} {}", quoted_name, kind, not_null));
+		match &col.generated {
+			Some(gen) => cols_sql.push(format!(
+				"{} {} GENERATED ALWAYS AS ({}) STORED {}",
+				quoted_name,
+				kind,
+				gen.to_sql(),
+				not_null
+			)),
+			None => {
+				cols_sql.push(format!("{} {} {}", quoted_name, kind, not_null))
+			}
+		}
 
-		match col.index {
+		match &col.index {
 			IndexKind::Primary => primary_indexes.push(quoted_name),
-			IndexKind::Unique => {
-				unique_indexes.push((col.name, vec![quoted_name]))
-			}
+			IndexKind::Unique => unique_indexes.push(quoted_name),
 			IndexKind::NamedUnique(n) => 'match_arm: loop {
-				for ind in unique_indexes.iter_mut() {
-					if ind.0 == n {
+				for ind in named_unique_indexes.iter_mut() {
+					if ind.0 == n.as_ref() {
 						ind.1.push(quoted_name);
 						break 'match_arm;
 					}
 				}
-				unique_indexes.push((n, vec![quoted_name]));
+				named_unique_indexes.push((n.as_ref(), vec![quoted_name]));
 				break;
 			},
-			IndexKind::Index => normal_indexes.push(col.name),
+			IndexKind::Index => normal_indexes.push(col),
 			IndexKind::None => {}
 		}
 	}
 
 	cols_sql.push(format!("PRIMARY KEY ({})", primary_indexes.join(", ")));
-	for ind in unique_indexes {
-		cols_sql.push(format!("UNIQUE ({})", ind.1.join(", ")));
+	for col in unique_indexes {
+		cols_sql.push(format!("UNIQUE ({})", col));
+	}
+	for (ind_name, cols) in named_unique_indexes {
+		// named so introspection can map it back to the NamedUnique
+		let constraint = format!("{}_{}_uidx", name.name(), ind_name);
+		cols_sql.push(format!(
+			"CONSTRAINT {} UNIQUE ({})",
+			Ident::new(&constraint),
+			cols.join(", ")
+		));
 	}
 
 	let mut sqls = vec![format!(
-		"CREATE TABLE IF NOT EXISTS \"{}\" ({})",
+		"CREATE TABLE IF NOT EXISTS {} ({})",
 		name,
 		cols_sql.join(", ")
 	)];
 
-	for ind in normal_indexes {
-		let index_name = format!("{}_{}_nidx", name, ind);
+	for col in normal_indexes {
+		let index_name = format!("{}_{}_nidx", name.name(), col.name);
+		let using = col
+			.kind
+			.index_method()
+			.map(|m| format!(" USING {m}"))
+			.unwrap_or_default();
 		sqls.push(format!(
-			"CREATE INDEX IF NOT EXISTS {} ON \"{}\" (\"{}\")",
-			index_name, name, ind
+			"CREATE INDEX IF NOT EXISTS {} ON {}{} ({})",
+			Ident::new(&index_name),
+			name,
+			using,
+			Ident::new(&col.name)
 		));
 	}
 
@@ -57,7 +85,7 @@ pub fn info_data_to_sql(name: &str, data: &[Column]) -> String {
 }
 
 pub fn quote(s: &str) -> String {
-	format!("\"{}\"", s)
+	Ident::new(s).to_string()
 }
 
 // maybe this is not important because table name can't be -
@@ -0,0 +1,28 @@
+use chuchi_postgres::database::{Config, PgConfig};
+use chuchi_postgres::Database;
+
+/// Connects to the database in `DATABASE_URL`
+///
+/// Only used by ignored tests, run them with
+/// `DATABASE_URL=postgres://... cargo test -- --ignored`.
+#[allow(dead_code)]
+pub async fn database() -> Database {
+	database_with(config()).await
+}
+
+#[allow(dead_code)]
+pub async fn database_with(cfg: Config) -> Database {
+	Database::with_cfg(cfg)
+		.await
+		.expect("could not connect to the database")
+}
+
+#[allow(dead_code)]
+pub fn config() -> Config {
+	let url = std::env::var("DATABASE_URL").expect("DATABASE_URL not set");
+
+	Config::from_pg_config(PgConfig {
+		url: Some(url),
+		..Default::default()
+	})
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use chuchi_postgres::connection::ConnectionOwned;
+use chuchi_postgres::Database;
+use deadpool_postgres::PoolConfig;
+
+async fn search_path(conn: &ConnectionOwned) -> String {
+	let [path]: [String; 1] = conn
+		.connection()
+		.query_one("SHOW search_path", &[])
+		.await
+		.unwrap();
+	path
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn schema_gets_reset() {
+	let mut cfg = common::config();
+	// only one connection so the same one get's reused
+	cfg.pg_config_mut().pool = Some(PoolConfig::new(1));
+	let db = Database::with_cfg(cfg).await.unwrap();
+
+	let default = search_path(&db.get().await.unwrap()).await;
+
+	let conn = db.get_with_schema("tenant_a").await.unwrap();
+	assert_eq!(search_path(&conn).await, "tenant_a");
+	drop(conn);
+
+	let conn = db.get().await.unwrap();
+	assert_eq!(search_path(&conn).await, default);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use chuchi_postgres::{filter, Error, FromRow};
+
+#[derive(Debug, FromRow)]
+struct Id {
+	id: i32,
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn json_path_exists() {
+	let db = common::database().await;
+	let conn = db.get().await.unwrap();
+	let conn = conn.connection();
+
+	conn.batch_execute(
+		"CREATE TEMP TABLE json_path (data jsonb NOT NULL); \
+		INSERT INTO json_path VALUES ('{\"a\": 1}'), ('{\"a\": -1}')",
+	)
+	.await
+	.unwrap();
+
+	let path = "$.a ? (@ > 0)";
+	let count = conn
+		.count("json_path", "data", filter!("data" @? &path))
+		.await
+		.unwrap();
+	assert_eq!(count, 1);
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn paginate() {
+	let db = common::database().await;
+	let conn = db.get().await.unwrap();
+	let conn = conn.connection();
+
+	conn.batch_execute(
+		"CREATE TEMP TABLE paginate (id int4 NOT NULL); \
+		INSERT INTO paginate SELECT generate_series(1, 25)",
+	)
+	.await
+	.unwrap();
+
+	// the limit and offset params get replaced
+	let min = 5;
+	let limit = 3;
+	let offset = 1;
+	let page = conn
+		.paginate::<Id>(
+			"paginate",
+			filter!("id" > &min ORDER "id" ASC LIMIT &limit OFFSET &offset),
+			1,
+			10,
+		)
+		.await
+		.unwrap();
+	assert_eq!(page.total, 20);
+	let ids: Vec<_> = page.items.iter().map(|i| i.id).collect();
+	assert_eq!(ids, (16..=25).collect::<Vec<_>>());
+
+	let res = conn
+		.paginate::<Id>("paginate", filter!(), u64::MAX, 10)
+		.await;
+	assert!(matches!(res, Err(Error::PageOutOfRange { .. })));
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use std::time::Duration;
+
+use chuchi_postgres::connection::ConnectionOwned;
+use chuchi_postgres::embed_migrations;
+use chuchi_postgres::migrations::{
+	Add, MigrationError, MigrationLock, MigrationSet, RunAll,
+};
+
+#[test]
+fn embedded_order() {
+	let set: MigrationSet = embed_migrations!("tests/migrations");
+
+	let migrations: Vec<_> =
+		set.iter().map(|m| (m.version, m.name.as_ref())).collect();
+	assert_eq!(
+		migrations,
+		[
+			(1, "init"),
+			(2, "add_email"),
+			(10, "index_email"),
+			(11, "index_name")
+		]
+	);
+
+	let first = set.iter().next().unwrap();
+	assert!(first.sql().unwrap().starts_with("CREATE TABLE users"));
+	assert_eq!(first.down.as_deref(), Some("DROP TABLE users;\n"));
+
+	let downs = set.iter().filter(|m| m.down.is_some()).count();
+	assert_eq!(downs, 2);
+
+	let transactions: Vec<_> = set.iter().map(|m| m.transaction).collect();
+	assert_eq!(transactions, [true, true, true, false]);
+}
+
+#[test]
+fn from_dir() {
+	let embedded: MigrationSet = embed_migrations!("tests/migrations");
+	let set = MigrationSet::from_dir(
+		std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+			.join("tests/migrations"),
+	)
+	.unwrap();
+
+	let migrations: Vec<_> = set
+		.iter()
+		.map(|m| (m.version, m.name.to_string(), m.down.clone(), m.transaction))
+		.collect();
+	let expected: Vec<_> = embedded
+		.iter()
+		.map(|m| (m.version, m.name.to_string(), m.down.clone(), m.transaction))
+		.collect();
+	assert_eq!(migrations, expected);
+
+	let checksums: Vec<_> = set.iter().map(|m| m.checksum()).collect();
+	let expected: Vec<_> = embedded.iter().map(|m| m.checksum()).collect();
+	assert_eq!(checksums, expected);
+}
+
+async fn drop_tables(conn: &ConnectionOwned, tables: &str) {
+	conn.connection()
+		.batch_execute(&format!("DROP TABLE IF EXISTS {tables}"))
+		.await
+		.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn skip_when_locked() {
+	let db = common::database_with(
+		common::config()
+			.migration_table("skip_migrations")
+			.migration_lock(MigrationLock::Skip),
+	)
+	.await;
+	let mut conn = db.get().await.unwrap();
+	let set = MigrationSet::new().add_with_down(
+		1,
+		"init",
+		"CREATE TABLE skip_t (id int);",
+		"",
+	);
+
+	let other = db.get().await.unwrap();
+	let key = "chuchi-postgres migrations skip_migrations";
+	other
+		.connection()
+		.execute("SELECT pg_advisory_lock(hashtext($1))", &[&key])
+		.await
+		.unwrap();
+
+	let res = db.migrations().run_all(&mut conn, &set).await.unwrap();
+	assert!(matches!(res, RunAll::Skipped));
+	let res = db.migrations().rollback_last(&mut conn, 1).await;
+	assert!(matches!(res, Err(MigrationError::Locked)));
+	let res = db.migrations().add(&mut conn, "add", "SELECT 1;").await;
+	assert_eq!(res.unwrap(), Add::Skipped);
+
+	// init creates the table even if the lock is held
+	let key2 = "chuchi-postgres migrations skip_migrations2";
+	other
+		.connection()
+		.execute("SELECT pg_advisory_lock(hashtext($1))", &[&key2])
+		.await
+		.unwrap();
+	let db2 = common::database_with(
+		common::config()
+			.migration_table("skip_migrations2")
+			.migration_lock(MigrationLock::Skip),
+	)
+	.await;
+	let res = db2.migrations().add(&mut conn, "add", "SELECT 1;").await;
+	assert_eq!(res.unwrap(), Add::Skipped);
+	conn.connection()
+		.batch_execute("SELECT * FROM skip_migrations2")
+		.await
+		.unwrap();
+
+	other
+		.connection()
+		.execute("SELECT pg_advisory_unlock_all()", &[])
+		.await
+		.unwrap();
+
+	let res = db.migrations().run_all(&mut conn, &set).await.unwrap();
+	let RunAll::Applied(applied) = res else {
+		panic!("expected the migrations to run");
+	};
+	assert_eq!(applied.len(), 1);
+
+	let res = db.migrations().run_all(&mut conn, &set).await.unwrap();
+	assert!(matches!(res, RunAll::Applied(a) if a.is_empty()));
+	let res = db.migrations().add(&mut conn, "add", "SELECT 1;").await;
+	assert_eq!(res.unwrap(), Add::Applied);
+
+	drop_tables(&conn, "skip_t, skip_migrations, skip_migrations2").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn cancelled_run_releases_lock() {
+	let db = common::database_with(
+		common::config()
+			.migration_table("cancel_migrations")
+			.migration_lock_timeout(Duration::from_secs(5)),
+	)
+	.await;
+	let mut conn = db.get().await.unwrap();
+
+	let slow = MigrationSet::new().add(1, "slow", "SELECT pg_sleep(2);");
+	let migrations = db.migrations();
+	let run = migrations.run_all(&mut conn, &slow);
+	let res = tokio::time::timeout(Duration::from_millis(200), run).await;
+	assert!(res.is_err());
+
+	// the dropped run must not keep the lock, the table lock of it's
+	// transaction is released once the sleep finished
+	let mut conn = db.get().await.unwrap();
+	let set = MigrationSet::new().add(1, "fast", "SELECT 1;");
+	let res = db.migrations().run_all(&mut conn, &set).await.unwrap();
+	assert!(matches!(res, RunAll::Applied(a) if a.len() == 1));
+
+	drop_tables(&conn, "cancel_migrations").await;
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn legacy_table_with_shared_names() {
+	let setup = common::database().await;
+	let conn = setup.get().await.unwrap();
+	drop_tables(&conn, "legacy_t, legacy_migrations").await;
+	conn.connection()
+		.batch_execute(
+			"CREATE TABLE legacy_migrations \
+			(name text PRIMARY KEY, datetime timestamp); \
+			INSERT INTO legacy_migrations VALUES ('init', now()); \
+			CREATE TABLE legacy_t (a int);",
+		)
+		.await
+		.unwrap();
+
+	let db = common::database_with(
+		common::config().migration_table("legacy_migrations"),
+	)
+	.await;
+	let mut conn = db.get().await.unwrap();
+
+	// both versions share a name, the first one was applied with add
+	let set = MigrationSet::new()
+		.add(1, "init", "CREATE TABLE legacy_t (a int);")
+		.add(2, "init", "ALTER TABLE legacy_t ADD COLUMN b int;");
+
+	let res = db.migrations().run_all(&mut conn, &set).await.unwrap();
+	let RunAll::Applied(applied) = res else {
+		panic!("expected the migrations to run");
+	};
+	let versions: Vec<_> = applied.iter().map(|m| m.version).collect();
+	assert_eq!(versions, [2]);
+
+	let res = db.migrations().run_all(&mut conn, &set).await.unwrap();
+	assert!(matches!(res, RunAll::Applied(a) if a.is_empty()));
+
+	let status = db.migrations().status(&conn, &set).await.unwrap();
+	let checksums: Vec<_> = status
+		.migrations
+		.iter()
+		.map(|m| m.applied.as_ref().unwrap().checksum.is_some())
+		.collect();
+	assert_eq!(checksums, [false, true]);
+	assert!(status.unknown.is_empty());
+
+	drop_tables(&conn, "legacy_t, legacy_migrations").await;
+}
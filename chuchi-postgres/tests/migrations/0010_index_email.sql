@@ -0,0 +1 @@
+CREATE INDEX ON users (email);
@@ -1,7 +1,11 @@
+mod common;
+
 use chuchi_postgres::enum_u16;
 use chuchi_postgres::row;
 use chuchi_postgres::row::NamedColumns;
 use chuchi_postgres::row::ToRowStatic;
+use chuchi_postgres::table::column::{ColumnKind, Generated, IndexKind};
+use chuchi_postgres::table::TableTemplate;
 use chuchi_postgres::{FromRow, TableTempl, ToRow, UniqueId};
 
 #[derive(Debug, TableTempl, FromRow, ToRow)]
@@ -17,6 +21,28 @@ pub struct Table {
 #[allow(dead_code)]
 pub struct Count(u32);
 
+#[derive(Debug, TableTempl, FromRow, ToRow)]
+#[tsvector(name = "search", config = "german", fields(title, body))]
+pub struct Article {
+	#[index(primary)]
+	pub id: UniqueId,
+	pub title: String,
+	pub body: Option<String>,
+}
+
+#[derive(Debug, TableTempl, FromRow, ToRow)]
+pub struct Membership {
+	#[index(primary)]
+	pub id: UniqueId,
+	#[unique(member)]
+	pub group_id: UniqueId,
+	#[unique(member)]
+	pub user_id: UniqueId,
+	#[index(unique)]
+	#[len(32)]
+	pub code: String,
+}
+
 enum_u16! {
 	#[derive(Debug)]
 	pub enum Type {
@@ -45,3 +71,74 @@ fn test_create_row() {
 		s
 	};
 }
+
+#[test]
+fn test_ts_vector() {
+	let info = Article::table_info();
+	let col = info.data().last().unwrap();
+	assert_eq!(col.name, "search");
+	assert_eq!(col.kind, ColumnKind::TsVector);
+	assert_eq!(col.index, IndexKind::Index);
+	assert_eq!(
+		col.generated,
+		Some(Generated::TsVector {
+			config: "german".into(),
+			columns: vec!["title".into(), "body".into()]
+		})
+	);
+	assert_eq!(
+		col.generated.as_ref().unwrap().to_sql(),
+		r#"to_tsvector('german', coalesce("title", '') || ' ' || coalesce("body", ''))"#
+	);
+
+	// the generated column is not selected or inserted
+	assert_eq!(Article::select_columns(), r#""id", "title", "body""#);
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn table_info_matches_database() {
+	let db = common::database().await;
+	let conn = db.get().await.unwrap();
+	conn.connection()
+		.batch_execute("DROP TABLE IF EXISTS article")
+		.await
+		.unwrap();
+
+	db.table_owned::<Article>("article")
+		.try_create()
+		.await
+		.unwrap();
+
+	let info = conn.connection().table_info("article").await.unwrap();
+	assert_eq!(info, Article::table_info());
+
+	conn.connection()
+		.batch_execute("DROP TABLE article")
+		.await
+		.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn table_info_named_unique() {
+	let db = common::database().await;
+	let conn = db.get().await.unwrap();
+	conn.connection()
+		.batch_execute("DROP TABLE IF EXISTS membership")
+		.await
+		.unwrap();
+
+	db.table_owned::<Membership>("membership")
+		.try_create()
+		.await
+		.unwrap();
+
+	let info = conn.connection().table_info("membership").await.unwrap();
+	assert_eq!(info, Membership::table_info());
+
+	conn.connection()
+		.batch_execute("DROP TABLE membership")
+		.await
+		.unwrap();
+}